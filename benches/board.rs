@@ -0,0 +1,192 @@
+/**This file contains benchmark that compares drawing the field with an entity for every tile
+ * to drawing it with the board renderer. Frames are drawn into an image in memory, so no window is opened.
+ * It also reveals a mostly empty field with a single click, so that flood reveal is tried on a huge field
+ */
+use game_oxide_framework::components::*;
+use game_oxide_framework::{layers, ui};
+use minesweeper_ecs::board_renderer::{BoardRenderer, BoardView};
+use minesweeper_ecs::fallback_art::{generate_fallback_theme, FALLBACK_IMAGE};
+use minesweeper_ecs::gameplay::{generate_grid, reveal_block, Board};
+use minesweeper_ecs::grid::{CellCoord, CellShape, Grid};
+use minesweeper_ecs::layout::ScreenLayout;
+use minesweeper_ecs::render::{render_frame, TextureAtlas};
+use minesweeper_ecs::Field;
+use nalgebra::{Vector2, Vector4};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::surface::Surface;
+use specs::{Builder, DispatcherBuilder, Entity, World, WorldExt};
+use std::time::{Duration, Instant};
+
+const FIELD_SIDE: usize = 500;
+const MINE_COUNT: u32 = 50_000;
+const WINDOW_SIZE: (i32, i32) = (1280, 800);
+const RESTARTS: u32 = 5;
+const FRAMES: u32 = 30;
+///Mines on the field that is revealed, there are few of them so that one click opens most of the field
+const FLOOD_MINE_COUNT: u32 = 500;
+
+fn make_world() -> World {
+    let mut world = World::new();
+    ui::register_ui_components(&mut world);
+    world.register::<Position>();
+    world.register::<Rectangle>();
+    world.register::<Colored>();
+    world.register::<Sprite>();
+    world.register::<Text>();
+    world.register::<Renderable>();
+    world.register::<BoardView>();
+    world.insert(ui::MouseData::default());
+    world
+}
+
+fn make_grid(mine_count: u32, rng: &mut StdRng) -> Grid<Field> {
+    generate_grid(
+        Grid::new(FIELD_SIDE, FIELD_SIDE, Field::default()),
+        mine_count,
+        1,
+        rng,
+    )
+}
+
+///Makes an entity for every tile, the way the field used to be made before the board renderer
+fn make_entity_per_tile(
+    world: &mut World,
+    tiles: &mut Vec<Entity>,
+    grid: &Grid<Field>,
+    layout: &ScreenLayout,
+) -> Result<(), String> {
+    for tile in tiles.drain(..) {
+        world.delete_entity(tile).map_err(|e| e.to_string())?;
+    }
+    for cell in grid.cells() {
+        let (position, button_size) = layout.button_rect(cell);
+        tiles.push(
+            ui::make_button_base(
+                world,
+                position,
+                Vector2::new(button_size, button_size),
+                Some(ui::Button {
+                    hovered_over: false,
+                    hovered_over_texture_name: Some("tile_selected".to_owned()),
+                    hovered_over_text: None,
+                    hovered_over_color: Some(sdl2::pixels::Color::RGBA(255, 0, 255, 120)),
+                    normal_texture_name: Some("tile_default".to_owned()),
+                    normal_text: None,
+                    normal_color: Some(sdl2::pixels::Color::RGBA(255, 255, 255, 120)),
+                }),
+                sdl2::pixels::Color::RGBA(255, 255, 255, 120),
+                layers::RenderLayers::Menu,
+            )
+            .with(Sprite {
+                name: "tile_default".to_owned(),
+                source_rect: None,
+                size: Vector2::new(layout.tile_size as u32, layout.tile_size as u32),
+                visible: true,
+            })
+            .build(),
+        );
+    }
+    world.maintain();
+    Ok(())
+}
+
+///Runs `action` given amount of times and returns average time it took
+fn measure<F: FnMut() -> Result<(), String>>(
+    times: u32,
+    mut action: F,
+) -> Result<Duration, String> {
+    let start = Instant::now();
+    for _ in 0..times {
+        action()?;
+    }
+    Ok(start.elapsed() / times)
+}
+
+fn main() -> Result<(), String> {
+    let mut rng = StdRng::seed_from_u64(0);
+    let layout = ScreenLayout::fit(
+        Vector2::new(WINDOW_SIZE.0, WINDOW_SIZE.1),
+        FIELD_SIDE,
+        FIELD_SIDE,
+        CellShape::Square,
+    );
+    println!(
+        "Benchmarking {0}x{0} field with {1} mines in {2}x{3} window, tile size is {4}",
+        FIELD_SIDE, MINE_COUNT, WINDOW_SIZE.0, WINDOW_SIZE.1, layout.tile_size
+    );
+
+    let mut canvas = Surface::new(
+        WINDOW_SIZE.0 as u32,
+        WINDOW_SIZE.1 as u32,
+        PixelFormatEnum::RGBA8888,
+    )?
+    .into_canvas()?;
+    let texture_creator = canvas.texture_creator();
+    let mut atlas = TextureAtlas::new(&texture_creator);
+    //fallback art is drawn in memory, so the benchmark does not depend on the assets
+    for (name, source) in generate_fallback_theme(&mut atlas)?.textures {
+        let rect = source.rect;
+        atlas.load(
+            Vector4::new(rect[0], rect[1], rect[2], rect[3]),
+            name,
+            FALLBACK_IMAGE.to_owned(),
+        )?;
+    }
+
+    let mut world = make_world();
+    let mut dispatcher = DispatcherBuilder::new()
+        .with(ui::ButtonUpdateSystem, "button_update_system", &[])
+        .build();
+    let mut tiles: Vec<Entity> = Vec::new();
+    let old_restart = measure(RESTARTS, || {
+        let grid = make_grid(MINE_COUNT, &mut rng);
+        make_entity_per_tile(&mut world, &mut tiles, &grid, &layout)
+    })?;
+    let old_frame = measure(FRAMES, || {
+        dispatcher.dispatch(&world);
+        render_frame(&world, &mut canvas, &atlas, None)
+    })?;
+
+    let mut world = make_world();
+    let board_renderer = BoardRenderer::new(&mut world, layout);
+    let new_restart = measure(RESTARTS, || {
+        world.insert(Board::new(make_grid(MINE_COUNT, &mut rng), MINE_COUNT, 1));
+        board_renderer.sync(&mut world, &layout, false)
+    })?;
+    let new_frame = measure(FRAMES, || {
+        board_renderer.sync(&mut world, &layout, false)?;
+        dispatcher.dispatch(&world);
+        render_frame(&world, &mut canvas, &atlas, None)
+    })?;
+
+    //one click on a mostly empty field reveals almost all of it
+    let mut grid = make_grid(FLOOD_MINE_COUNT, &mut rng);
+    let empty_tile = grid
+        .iter()
+        .find(|(_, field)| !field.has_bomb() && !field.border)
+        .map(|(cell, _)| cell)
+        .unwrap_or_default();
+    let mut revealed: Vec<CellCoord> = Vec::new();
+    let reveal = measure(1, || {
+        reveal_block(empty_tile, &mut grid, &mut revealed);
+        Ok(())
+    })?;
+
+    println!("                    restart        frame");
+    println!(
+        "entity per tile  {:>10.2?}   {:>10.2?}",
+        old_restart, old_frame
+    );
+    println!(
+        "board renderer   {:>10.2?}   {:>10.2?}",
+        new_restart, new_frame
+    );
+    println!(
+        "flood reveal of {} tiles took {:.2?}",
+        revealed.len(),
+        reveal
+    );
+    Ok(())
+}
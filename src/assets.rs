@@ -1,72 +1,317 @@
-/**This file  contains function that loads all of the textures used by the game=
+/**This file  contains function that loads all of the textures used by the game
+ * and themes that describe where those textures are taken from
 */
+use crate::options::{MAX_MINES_PER_CELL, MAX_TILE_VALUE};
+use crate::render::TextureAtlas;
 use nalgebra::Vector4;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 
-///Loads all of the textures that are used by the game
-pub fn load_textures(
-    texture_manager: &mut game_oxide_framework::texture_manager::TextureManager,
-) -> Result<(), String> {
-    texture_manager.load(
-        Vector4::new(0, 0, 16, 16),
-        "tile_default".to_owned(),
-        "./assets/minesweeper.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(0, 16, 16, 16),
-        "tile_selected".to_owned(),
-        "./assets/minesweeper.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(0, 48, 16, 16),
-        "tile_question".to_owned(),
-        "./assets/minesweeper.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(0, 64, 16, 16),
-        "tile_bomb".to_owned(),
-        "./assets/minesweeper.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(0, 32, 16, 16),
-        "tile_flag".to_owned(),
-        "./assets/minesweeper.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(0, 0, 20, 20),
-        "face_default".to_owned(),
-        "./assets/face.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(20, 0, 20, 20),
-        "face_loose".to_owned(),
-        "./assets/face.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(40, 0, 20, 20),
-        "face_win".to_owned(),
-        "./assets/face.png".to_owned(),
-    )?;
-    texture_manager.load(
-        Vector4::new(60, 0, 20, 20),
-        "face_hover".to_owned(),
-        "./assets/face.png".to_owned(),
-    )?;
-    //for some reason in the original file for minesweeper it went from 8 to 0 (top to bottom),
-    // i decided to keep it that way so we have to do a bit of a weird loop
-    //can you even get an 8?
-    for i in (0..=8).rev() {
-        texture_manager.load(
-            Vector4::new(0, i * 16 + 128, 16, 16),
-            "tile_".to_owned() + (8 - i).to_string().as_str(),
-            "./assets/minesweeper.png".to_owned(),
-        )?;
+///Environment variable that overrides where the assets are
+pub const ASSETS_DIR_VAR: &str = "ASSETS_DIR";
+///Directory inside the assets directory where every subdirectory with `theme.json` in it is a theme
+pub const THEMES_DIR: &str = "themes";
+///Name of the theme that is built into the game
+pub const DEFAULT_THEME: &str = "default";
+
+///States of the tiles that only multi-mine fields have: numbers above 8 and stacked flags.
+/// Texture names are these states with `tile_` or `hex_` in front of them
+pub fn multi_mine_tile_states() -> Vec<String> {
+    (9..=MAX_TILE_VALUE)
+        .map(|value| value.to_string())
+        .chain((2..=MAX_MINES_PER_CELL).map(|flags| format!("flag_{}", flags)))
+        .collect()
+}
+
+///Where to find a single texture
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TextureSource {
+    ///Path to the image, relative to the directory of the theme
+    pub file: String,
+    ///x, y, width and height of the texture in the image
+    pub rect: [i32; 4],
+}
+
+///Set of textures that replaces the default look of the game.
+/// Textures that theme does not have are taken from the default theme
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub textures: BTreeMap<String, TextureSource>,
+    ///Directory that paths of the textures are relative to
+    #[serde(skip)]
+    pub directory: PathBuf,
+}
+
+impl Theme {
+    ///Theme made from the art in the assets folder, this is what game originally looked like
+    pub fn default_theme() -> Theme {
+        let mut textures: BTreeMap<String, TextureSource> = BTreeMap::new();
+        let mut add = |name: &str, file: &str, rect: [i32; 4]| {
+            textures.insert(
+                name.to_owned(),
+                TextureSource {
+                    file: file.to_owned(),
+                    rect,
+                },
+            );
+        };
+        add("tile_default", "minesweeper.png", [0, 0, 16, 16]);
+        add("tile_selected", "minesweeper.png", [0, 16, 16, 16]);
+        add("tile_question", "minesweeper.png", [0, 48, 16, 16]);
+        add("tile_bomb", "minesweeper.png", [0, 64, 16, 16]);
+        add("tile_flag", "minesweeper.png", [0, 32, 16, 16]);
+        add("face_default", "face.png", [0, 0, 20, 20]);
+        add("face_loose", "face.png", [20, 0, 20, 20]);
+        add("face_win", "face.png", [40, 0, 20, 20]);
+        add("face_hover", "face.png", [60, 0, 20, 20]);
+        //faces that show whose turn it is when two players take turns
+        add("face_player_1", "face_players.png", [0, 0, 20, 20]);
+        add("face_player_2", "face_players.png", [20, 0, 20, 20]);
+        //for some reason in the original file for minesweeper it went from 8 to 0 (top to bottom),
+        // i decided to keep it that way so we have to do a bit of a weird loop
+        //can you even get an 8?
+        for i in (0..=8).rev() {
+            add(
+                ("tile_".to_owned() + (8 - i).to_string().as_str()).as_str(),
+                "minesweeper.png",
+                [0, i * 16 + 128, 16, 16],
+            );
+        }
+        //original art does not have textures for multi-mine fields, so they are in a separate row
+        for (i, state) in multi_mine_tile_states().iter().enumerate() {
+            add(
+                ("tile_".to_owned() + state.as_str()).as_str(),
+                "multimine.png",
+                [i as i32 * 16, 0, 16, 16],
+            );
+        }
+        //hex tiles are a single row with the same textures as the square ones
+        let hex_states = ["default", "selected", "question", "bomb", "flag"]
+            .iter()
+            .map(|state| state.to_string())
+            .chain((0..=8).map(|value| value.to_string()))
+            .chain(multi_mine_tile_states());
+        for (i, state) in hex_states.enumerate() {
+            add(
+                ("hex_".to_owned() + state.as_str()).as_str(),
+                "hex.png",
+                [i as i32 * 16, 0, 16, 16],
+            );
+        }
+        for i in 0..=9 {
+            add(i.to_string().as_str(), "numbers.png", [i * 20, 0, 20, 36]);
+        }
+        //minus sign goes right after 9, it's used when there are more flags than mines
+        add("minus", "numbers.png", [200, 0, 20, 36]);
+        Theme {
+            name: DEFAULT_THEME.to_owned(),
+            textures,
+            directory: assets_dir(),
+        }
+    }
+
+    ///Loads theme from `theme.json` in the given directory
+    pub fn load(directory: &Path) -> Result<Theme, String> {
+        let path = directory.join("theme.json");
+        let data = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let mut theme: Theme = serde_json::from_str(&data)
+            .map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+        theme.directory = directory.to_owned();
+        Ok(theme)
+    }
+}
+
+///Something that is wrong with one of the assets
+#[derive(Clone, Debug, PartialEq)]
+pub enum AssetError {
+    ///File does not exist and there is nothing to replace it with
+    Missing { name: String, path: PathBuf },
+    ///File exists, but could not be loaded
+    Malformed {
+        name: String,
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Missing { name, path } => write!(f, "{} is missing: {:?}", name, path),
+            AssetError::Malformed { name, path, reason } => {
+                write!(
+                    f,
+                    "{} could not be loaded from {:?}: {}",
+                    name, path, reason
+                )
+            }
+        }
+    }
+}
+
+///Turns list of errors into a message that can be shown to the player
+pub fn describe_errors(errors: &[AssetError]) -> String {
+    let mut message = format!(
+        "Some of the game files could not be loaded from {:?}.\n\
+         Set {} to the directory with the assets to use a different one.\n",
+        assets_dir(),
+        ASSETS_DIR_VAR
+    );
+    for error in errors {
+        message += &format!("\n{}", error);
+    }
+    message
+}
+
+///Shows window with the errors, returns the message that was shown.
+/// If the window can't be shown message is only printed
+pub fn show_errors(window: &sdl2::video::Window, errors: &[AssetError]) -> String {
+    let message = describe_errors(errors);
+    println!("{}", message);
+    let _ = sdl2::messagebox::show_simple_message_box(
+        sdl2::messagebox::MessageBoxFlag::ERROR,
+        "Minesweeper",
+        &message,
+        window,
+    );
+    message
+}
+
+///Directory with the assets. It's taken from `ASSETS_DIR` if it is set,
+/// then from the directory of the executable, and then from the working directory
+pub fn assets_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os(ASSETS_DIR_VAR) {
+        return PathBuf::from(dir);
+    }
+    let next_to_exe = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("assets")));
+    match next_to_exe {
+        Some(dir) if dir.is_dir() => dir,
+        _ => PathBuf::from("./assets"),
+    }
+}
+
+///Returns default theme followed by every theme from the themes directory.
+/// Themes that fail to load are skipped so that one broken theme does not break the game
+pub fn load_themes(themes_dir: &Path) -> Vec<Theme> {
+    let mut themes = vec![Theme::default_theme()];
+    let entries = match std::fs::read_dir(themes_dir) {
+        Ok(entries) => entries,
+        Err(_) => return themes,
+    };
+    let mut directories: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join("theme.json").is_file())
+        .collect();
+    //read_dir order is not defined, but theme order should not change between launches
+    directories.sort();
+    for directory in directories {
+        match Theme::load(&directory) {
+            Ok(theme) if themes.iter().any(|other| other.name == theme.name) => {
+                println!(
+                    "Skipping theme {:?}: name {} is already used",
+                    directory, theme.name
+                )
+            }
+            Ok(theme) => themes.push(theme),
+            Err(e) => println!("Skipping theme: {}", e),
+        }
+    }
+    themes
+}
+
+///Font that is shipped with the art, relative to the assets directory
+pub const FONT_PATH: &str = "fonts/Roboto-Medium.ttf";
+
+///Fonts that usually come with the system, used when the font from the assets is missing
+const SYSTEM_FONTS: [&str; 6] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+///Returns path to the font from the assets, or to a system font if it's missing
+pub fn find_font() -> Result<PathBuf, AssetError> {
+    let path = assets_dir().join(FONT_PATH);
+    std::iter::once(path.clone())
+        .chain(SYSTEM_FONTS.iter().map(PathBuf::from))
+        .find(|path| path.is_file())
+        .ok_or(AssetError::Missing {
+            name: "Font".to_owned(),
+            path,
+        })
+}
+
+///Loads all of the textures that are used by the game from the given theme.
+/// Textures whose files are missing are taken from the default theme,
+/// and if those are missing too, from the `fallback` theme that is drawn by the game.
+/// Loading does not stop on the first error, so that all of the errors can be shown at once
+pub fn load_textures<T>(
+    atlas: &mut TextureAtlas<T>,
+    theme: &Theme,
+    fallback: Option<&Theme>,
+) -> Result<(), Vec<AssetError>> {
+    let default = Theme::default_theme();
+    let mut errors = Vec::new();
+    for (name, default_source) in default.textures.iter() {
+        let candidates = [
+            theme
+                .textures
+                .get(name)
+                .map(|source| (&theme.directory, source)),
+            Some((&default.directory, default_source)),
+            fallback.and_then(|fallback| {
+                fallback
+                    .textures
+                    .get(name)
+                    .map(|source| (&fallback.directory, source))
+            }),
+        ];
+        //images drawn by the game are only in the atlas
+        let mut existing = candidates.iter().flatten().filter(|(directory, source)| {
+            let path = directory.join(&source.file);
+            atlas.has_image(&path.to_string_lossy()) || path.is_file()
+        });
+        let (directory, source) = match existing.next() {
+            Some(candidate) => *candidate,
+            None => {
+                errors.push(AssetError::Missing {
+                    name: format!("Texture {}", name),
+                    path: default.directory.join(&default_source.file),
+                });
+                continue;
+            }
+        };
+        let path = directory.join(&source.file);
+        if let Err(reason) = atlas.load(
+            Vector4::new(
+                source.rect[0],
+                source.rect[1],
+                source.rect[2],
+                source.rect[3],
+            ),
+            name.clone(),
+            path.to_string_lossy().into_owned(),
+        ) {
+            errors.push(AssetError::Malformed {
+                name: format!("Texture {}", name),
+                path,
+                reason,
+            });
+        }
     }
-    for i in (0..=9) {
-        texture_manager.load(
-            Vector4::new(i * 20, 0, 20, 36),
-            i.to_string(),
-            "./assets/numbers.png".to_owned(),
-        )?;
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
     }
-    Ok(())
 }
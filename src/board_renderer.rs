@@ -0,0 +1,205 @@
+/**This file contains renderer that draws the field from the grid in one pass.
+ * Whole field is a single renderable that only walks the tiles that are visible,
+ * so neither restarting nor drawing depends on the size of the field
+ */
+use crate::coop::PlayerCursors;
+use crate::gameplay::Board;
+use crate::grid::{CellCoord, CellShape};
+use crate::layout::ScreenLayout;
+use crate::render::{draw_texture, TextureAtlas};
+use crate::Field;
+use game_oxide_framework::components::*;
+use game_oxide_framework::layers;
+use nalgebra::Vector2;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget};
+use specs::{Builder, Component, Entity, HashMapStorage, World, WorldExt};
+
+///Colors of the players in co-op games, used for their flags and cursors
+const PLAYER_COLORS: [(u8, u8, u8); 6] = [
+    (230, 60, 60),
+    (60, 110, 230),
+    (60, 190, 80),
+    (230, 170, 40),
+    (170, 80, 210),
+    (40, 190, 190),
+];
+
+pub fn player_color(player: usize) -> sdl2::pixels::Color {
+    let (r, g, b) = PLAYER_COLORS[player % PLAYER_COLORS.len()];
+    sdl2::pixels::Color::RGBA(r, g, b, 160)
+}
+
+///Color of the tile that is not hovered over, border tiles are colored differently.
+/// In co-op games tiles other players point at and their flags get the color of the player
+fn tile_color(field: &Field, cursor: Option<usize>) -> sdl2::pixels::Color {
+    match cursor.or(field.owner) {
+        Some(player) => player_color(player),
+        None => sdl2::pixels::Color::RGBA(if field.border { 0 } else { 255 }, 255, 255, 120),
+    }
+}
+
+///Prefix of the names of the textures for the given shape of cells
+fn texture_prefix(shape: CellShape) -> &'static str {
+    match shape {
+        CellShape::Square => "tile_",
+        CellShape::Hex => "hex_",
+    }
+}
+
+///Name of the texture that shows the field in its current state
+pub fn tile_texture(field: &Field, shape: CellShape) -> String {
+    let state = if field.revealed {
+        if field.has_bomb() {
+            "bomb".to_owned()
+        } else {
+            field.value.to_string()
+        }
+    } else if field.flags > 1 {
+        format!("flag_{}", field.flags)
+    } else if field.is_flagged() {
+        "flag".to_owned()
+    } else if field.questioned {
+        "question".to_owned()
+    } else {
+        "default".to_owned()
+    };
+    texture_prefix(shape).to_owned() + state.as_str()
+}
+
+///Field as it's seen through the camera, entity with it draws the board resource in place of the tiles
+#[derive(Component, Clone, Debug)]
+#[storage(HashMapStorage)]
+pub struct BoardView {
+    pub layout: ScreenLayout,
+    ///Field is hidden while the game is paused, only the tiles without textures are drawn
+    pub hidden: bool,
+}
+
+///Returns all tiles that are visible with the given layout
+fn visible_tiles(layout: &ScreenLayout) -> Vec<CellCoord> {
+    //only the tiles in the window are checked, so this does not depend on the size of the field
+    let first =
+        |origin: i32, view_start: i32, step: i32| ((view_start - origin) / step).max(0) as usize;
+    let last = |origin: i32, view_end: i32, step: i32, count: usize| {
+        (((view_end - origin) / step + 1).max(0) as usize).min(count)
+    };
+    let rows = first(layout.field_origin.y, 0, layout.row_step())
+        ..last(
+            layout.field_origin.y,
+            layout.window_size.y,
+            layout.row_step(),
+            layout.field_size.y,
+        );
+    //odd hex rows are shifted right, so one more column can be seen on the left side
+    let columns = first(layout.field_origin.x, 0, layout.tile_size).saturating_sub(1)
+        ..last(
+            layout.field_origin.x,
+            layout.window_size.x,
+            layout.tile_size,
+            layout.field_size.x,
+        );
+    let mut tiles: Vec<CellCoord> = Vec::new();
+    for row in rows {
+        for column in columns.clone() {
+            let cell = CellCoord::new(column, row);
+            if layout.is_tile_visible(cell) {
+                tiles.push(cell);
+            }
+        }
+    }
+    tiles
+}
+
+///Draws every visible tile of the board. Tile under the mouse is highlighted
+/// the same way the button system highlights buttons
+pub fn draw_board<R: RenderTarget, T>(
+    canvas: &mut Canvas<R>,
+    atlas: &TextureAtlas<T>,
+    view: &BoardView,
+    board: &Board,
+    cursors: Option<&PlayerCursors>,
+    mouse: Option<Vector2<i32>>,
+) -> Result<(), String> {
+    let layout = &view.layout;
+    let grid = &board.grid;
+    let hovered = mouse.and_then(|mouse| layout.hit_test(mouse));
+    let cursor_at = |cell: CellCoord| {
+        cursors.and_then(|cursors| cursors.cursors.iter().position(|c| *c == Some(cell)))
+    };
+    let tile_size = Vector2::new(layout.tile_size as u32, layout.tile_size as u32);
+    for tile in visible_tiles(layout) {
+        let field = &grid[tile];
+        let cursor = cursor_at(tile);
+        let texture = tile_texture(field, grid.shape());
+        let (name, color) = if field.revealed {
+            let color = match cursor {
+                Some(player) => player_color(player),
+                None => sdl2::pixels::Color::RGBA(255, 255, 255, 120),
+            };
+            (texture, color)
+        } else if hovered == Some(tile) {
+            let texture = if field.is_flagged() || field.questioned {
+                texture
+            } else {
+                texture_prefix(grid.shape()).to_owned() + "selected"
+            };
+            (texture, sdl2::pixels::Color::RGBA(255, 0, 255, 120))
+        } else {
+            (texture, tile_color(field, cursor))
+        };
+        let (position, button_size) = layout.button_rect(tile);
+        canvas.set_draw_color(color);
+        canvas.fill_rect(Rect::new(
+            position.x,
+            position.y,
+            button_size as u32,
+            button_size as u32,
+        ))?;
+        if !view.hidden {
+            draw_texture(canvas, atlas, &name, position, tile_size)?;
+        }
+    }
+    Ok(())
+}
+
+///Keeps the board view in sync with the camera and the state of the game
+pub struct BoardRenderer {
+    ///Entity that draws the whole board
+    entity: Entity,
+}
+
+impl BoardRenderer {
+    pub fn new(world: &mut World, layout: ScreenLayout) -> Self {
+        world.register::<BoardView>();
+        let entity = world
+            .create_entity()
+            .with(BoardView {
+                layout,
+                hidden: false,
+            })
+            .with(Renderable::new(true, layers::RenderLayers::Menu as u32))
+            .build();
+        Self { entity }
+    }
+
+    ///Updates the view to show the field through the camera, `hidden` hides the field while the game is paused
+    pub fn sync(
+        &self,
+        world: &mut World,
+        layout: &ScreenLayout,
+        hidden: bool,
+    ) -> Result<(), String> {
+        world
+            .write_storage::<BoardView>()
+            .insert(
+                self.entity,
+                BoardView {
+                    layout: *layout,
+                    hidden,
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
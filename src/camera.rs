@@ -0,0 +1,201 @@
+/**This file contains camera that lets player look at the part of the field
+ * when the whole field does not fit into the window
+ */
+use crate::grid::CellCoord;
+use crate::layout::{ScreenLayout, CONTROLS_PANEL_SIZE};
+use nalgebra::Vector2;
+
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 4.0;
+///How much zoom changes with one step of the wheel or one key press
+pub const ZOOM_STEP: f32 = 1.25;
+
+///Camera moves the field and changes the size of the tiles, it does not affect the panel
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub zoom: f32,
+    ///How far the camera moved from where the layout put the field, in pixels
+    pub offset: Vector2<i32>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset: Vector2::new(0, 0),
+        }
+    }
+}
+
+///Returns the lowest and the highest position the start of the field can have on one axis,
+/// so that field stays inside of the view if it's smaller and covers it if it's bigger
+fn origin_limits(view_start: i32, view_end: i32, field_size: i32) -> (i32, i32) {
+    let end_aligned = view_end - field_size;
+    (view_start.min(end_aligned), view_start.max(end_aligned))
+}
+
+impl Camera {
+    ///Layout of the field as it's seen through the camera
+    pub fn view(&self, layout: &ScreenLayout) -> ScreenLayout {
+        ScreenLayout {
+            tile_size: ((layout.tile_size as f32 * self.zoom).round() as i32).max(1),
+            field_origin: layout.field_origin - self.offset,
+            ..*layout
+        }
+    }
+
+    ///Moves the camera by the given amount of pixels
+    pub fn pan(&mut self, delta: Vector2<i32>, layout: &ScreenLayout) {
+        self.offset += delta;
+        self.clamp(layout);
+    }
+
+    ///Multiplies zoom by `factor`, tile under `anchor` stays where it was on screen
+    pub fn zoom_by(&mut self, factor: f32, anchor: Vector2<i32>, layout: &ScreenLayout) {
+        let before = self.view(layout);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let after = self.view(layout);
+        let scale = after.tile_size as f32 / before.tile_size as f32;
+        let from_anchor = (before.field_origin - anchor).cast::<f32>() * scale;
+        let origin =
+            anchor + Vector2::new(from_anchor.x.round() as i32, from_anchor.y.round() as i32);
+        self.offset = layout.field_origin - origin;
+        self.clamp(layout);
+    }
+
+    ///Moves the camera so that field does not go further than it has to
+    pub fn clamp(&mut self, layout: &ScreenLayout) {
+        let view = self.view(layout);
+        let field_size = view.field_pixels();
+        let (min_x, max_x) = origin_limits(0, layout.window_size.x, field_size.x);
+        let (min_y, max_y) = origin_limits(CONTROLS_PANEL_SIZE, layout.window_size.y, field_size.y);
+        let origin = Vector2::new(
+            view.field_origin.x.clamp(min_x, max_x),
+            view.field_origin.y.clamp(min_y, max_y),
+        );
+        self.offset = layout.field_origin - origin;
+    }
+
+    ///Moves the camera just enough for the tile to be fully visible
+    pub fn show_tile(&mut self, cell: CellCoord, layout: &ScreenLayout) {
+        let view = self.view(layout);
+        let position = view.tile_position(cell);
+        let shift = |start: i32, view_start: i32, view_end: i32| {
+            if start < view_start {
+                start - view_start
+            } else if start + view.tile_size > view_end {
+                start + view.tile_size - view_end
+            } else {
+                0
+            }
+        };
+        self.pan(
+            Vector2::new(
+                shift(position.x, 0, layout.window_size.x),
+                shift(position.y, CONTROLS_PANEL_SIZE, layout.window_size.y),
+            ),
+            layout,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::CellShape;
+
+    ///400x300 below the panel, with tiles of 20 pixels the field is `width`x`height` tiles
+    fn layout(width: usize, height: usize) -> ScreenLayout {
+        ScreenLayout {
+            window_size: Vector2::new(400, 400),
+            tile_size: 20,
+            field_origin: Vector2::new(0, CONTROLS_PANEL_SIZE),
+            field_size: Vector2::new(width, height),
+            shape: CellShape::Square,
+        }
+    }
+
+    ///Top left and bottom right corners of the field on screen
+    fn field_corners(camera: &Camera, layout: &ScreenLayout) -> (Vector2<i32>, Vector2<i32>) {
+        let view = camera.view(layout);
+        (view.field_origin, view.field_origin + view.field_pixels())
+    }
+
+    #[test]
+    fn zoom_stays_within_the_limits() {
+        let layout = layout(40, 30);
+        let anchor = Vector2::new(200, 250);
+        let mut camera = Camera::default();
+        for _ in 0..50 {
+            camera.zoom_by(ZOOM_STEP, anchor, &layout);
+        }
+        assert_eq!(camera.zoom, MAX_ZOOM);
+        camera.zoom_by(1000.0, anchor, &layout);
+        assert_eq!(camera.zoom, MAX_ZOOM);
+        for _ in 0..50 {
+            camera.zoom_by(1.0 / ZOOM_STEP, anchor, &layout);
+        }
+        assert_eq!(camera.zoom, MIN_ZOOM);
+        camera.zoom_by(0.001, anchor, &layout);
+        assert_eq!(camera.zoom, MIN_ZOOM);
+    }
+
+    #[test]
+    fn point_under_the_anchor_stays_in_place() {
+        let layout = layout(40, 30);
+        let anchor = Vector2::new(200, 250);
+        let mut camera = Camera::default();
+        //point of the field under the anchor, in tiles
+        let under_anchor = |camera: &Camera| {
+            let view = camera.view(&layout);
+            (anchor - view.field_origin).cast::<f32>() / view.tile_size as f32
+        };
+        let before = under_anchor(&camera);
+        let tile = camera.view(&layout).tile_at(anchor);
+        camera.zoom_by(2.0, anchor, &layout);
+        assert_eq!(camera.view(&layout).tile_size, 40);
+        assert_eq!(under_anchor(&camera), before);
+        assert_eq!(camera.view(&layout).tile_at(anchor), tile);
+        camera.zoom_by(0.5, anchor, &layout);
+        assert_eq!(under_anchor(&camera), before);
+    }
+
+    #[test]
+    fn big_field_always_covers_the_view() {
+        let layout = layout(40, 30);
+        let mut camera = Camera::default();
+        camera.pan(Vector2::new(-1000, -1000), &layout);
+        assert_eq!(
+            field_corners(&camera, &layout).0,
+            Vector2::new(0, CONTROLS_PANEL_SIZE)
+        );
+        camera.pan(Vector2::new(5000, 5000), &layout);
+        assert_eq!(field_corners(&camera, &layout).1, layout.window_size);
+        //zooming out near the edge pulls the field back so that it still covers the view
+        camera.zoom_by(0.5, Vector2::new(399, 399), &layout);
+        assert_eq!(field_corners(&camera, &layout).1, layout.window_size);
+    }
+
+    #[test]
+    fn small_field_stays_inside_the_view() {
+        //200x100 field in the middle of the view
+        let layout = ScreenLayout {
+            field_origin: Vector2::new(100, 200),
+            ..layout(10, 5)
+        };
+        let mut camera = Camera::default();
+        for delta in [
+            Vector2::new(-1000, -1000),
+            Vector2::new(5000, 5000),
+            Vector2::new(-30, 70),
+        ] {
+            camera.pan(delta, &layout);
+            let (top_left, bottom_right) = field_corners(&camera, &layout);
+            assert!(top_left.x >= 0 && top_left.y >= CONTROLS_PANEL_SIZE);
+            assert!(bottom_right.x <= layout.window_size.x);
+            assert!(bottom_right.y <= layout.window_size.y);
+        }
+        camera.pan(Vector2::new(-1000, -1000), &layout);
+        assert_eq!(field_corners(&camera, &layout).1, Vector2::new(400, 400));
+    }
+}
@@ -0,0 +1,251 @@
+/**This file contains game clock that measures how long the game took.
+ * Clock does not read the time directly, but asks the time source for it,
+ * so that tests can control the time
+ */
+use std::time::{Duration, Instant};
+
+///Something that can tell the current time
+pub trait TimeSource {
+    fn now(&self) -> Instant;
+}
+
+///Time source that uses the real monotonic clock
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+///Clock that can be started, paused and stopped.
+/// Time spent on pause does not count
+#[derive(Clone, Debug)]
+pub struct GameClock<T: TimeSource = SystemClock> {
+    source: T,
+    started_at: Option<Instant>,
+    stopped_at: Option<Instant>,
+    paused_at: Option<Instant>,
+    ///Total time spent on pause
+    paused_for: Duration,
+}
+
+impl Default for GameClock<SystemClock> {
+    fn default() -> Self {
+        GameClock::new(SystemClock)
+    }
+}
+
+impl<T: TimeSource> GameClock<T> {
+    pub fn new(source: T) -> Self {
+        Self {
+            source,
+            started_at: None,
+            stopped_at: None,
+            paused_at: None,
+            paused_for: Duration::ZERO,
+        }
+    }
+
+    ///Starts the clock, does nothing if clock was already started
+    pub fn start(&mut self) {
+        if self.started_at.is_none() {
+            self.started_at = Some(self.source.now());
+        }
+    }
+
+    ///Starts the clock as if it has already been running for given time,
+    /// used to continue saved games
+    pub fn start_from(&mut self, elapsed: Duration) {
+        self.reset();
+        let now = self.source.now();
+        //on some platforms instant can't go further back than the start of the system,
+        //in that case we have to start from 0
+        self.started_at = Some(now.checked_sub(elapsed).unwrap_or(now));
+    }
+
+    ///Stops the clock for good, elapsed time will not change anymore
+    pub fn stop(&mut self) {
+        if self.started_at.is_some() && self.stopped_at.is_none() {
+            self.stopped_at = Some(self.paused_at.unwrap_or_else(|| self.source.now()));
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if self.paused_at.is_none() {
+            self.paused_at = Some(self.source.now());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            //pause that started after the clock was stopped should not change anything
+            if self.stopped_at.is_none() {
+                self.paused_for += self.source.now().saturating_duration_since(paused_at);
+            }
+        }
+    }
+
+    ///Returns clock to the state it had when it was created
+    pub fn reset(&mut self) {
+        self.started_at = None;
+        self.stopped_at = None;
+        self.paused_at = None;
+        self.paused_for = Duration::ZERO;
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped_at.is_some()
+    }
+
+    ///How long the clock has been running, not counting the pauses
+    pub fn elapsed(&self) -> Duration {
+        let started_at = match self.started_at {
+            Some(started_at) => started_at,
+            None => return Duration::ZERO,
+        };
+        let end = self
+            .stopped_at
+            .or(self.paused_at)
+            .unwrap_or_else(|| self.source.now());
+        end.saturating_duration_since(started_at)
+            .saturating_sub(self.paused_for)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    ///Time source that only moves when the test moves it
+    #[derive(Clone)]
+    struct FakeTime(Rc<Cell<Instant>>);
+
+    impl FakeTime {
+        fn new() -> Self {
+            FakeTime(Rc::new(Cell::new(Instant::now())))
+        }
+
+        fn advance(&self, seconds: u64) {
+            self.0.set(self.0.get() + Duration::from_secs(seconds));
+        }
+    }
+
+    impl TimeSource for FakeTime {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    fn fake_clock() -> (GameClock<FakeTime>, FakeTime) {
+        let time = FakeTime::new();
+        (GameClock::new(time.clone()), time)
+    }
+
+    #[test]
+    fn clock_only_counts_after_the_first_reveal_starts_it() {
+        let (mut clock, time) = fake_clock();
+        time.advance(5);
+        assert!(!clock.is_started());
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+        clock.start();
+        time.advance(3);
+        assert_eq!(clock.elapsed(), Duration::from_secs(3));
+        //later reveals don't start the clock again
+        clock.start();
+        time.advance(2);
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn time_on_pause_does_not_count() {
+        let (mut clock, time) = fake_clock();
+        clock.start();
+        time.advance(2);
+        clock.pause();
+        time.advance(10);
+        assert_eq!(clock.elapsed(), Duration::from_secs(2));
+        //pausing twice keeps the time of the first pause
+        clock.pause();
+        time.advance(10);
+        clock.resume();
+        assert_eq!(clock.elapsed(), Duration::from_secs(2));
+        time.advance(3);
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+        clock.pause();
+        time.advance(7);
+        clock.resume();
+        time.advance(1);
+        assert_eq!(clock.elapsed(), Duration::from_secs(6));
+    }
+
+    #[test]
+    fn stopped_clock_does_not_change() {
+        let (mut clock, time) = fake_clock();
+        clock.start();
+        time.advance(4);
+        clock.stop();
+        assert!(clock.is_stopped());
+        time.advance(100);
+        assert_eq!(clock.elapsed(), Duration::from_secs(4));
+        clock.pause();
+        time.advance(5);
+        clock.resume();
+        clock.start();
+        assert_eq!(clock.elapsed(), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn clock_stopped_on_pause_keeps_the_time_of_the_pause() {
+        let (mut clock, time) = fake_clock();
+        clock.start();
+        time.advance(4);
+        clock.pause();
+        time.advance(6);
+        clock.stop();
+        clock.resume();
+        time.advance(6);
+        assert_eq!(clock.elapsed(), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn loaded_game_continues_from_saved_time() {
+        let (mut clock, time) = fake_clock();
+        //loading a save replaces whatever the clock had before
+        clock.start();
+        time.advance(50);
+        clock.pause();
+        clock.start_from(Duration::from_secs(30));
+        assert!(clock.is_started());
+        assert!(!clock.is_stopped());
+        assert_eq!(clock.elapsed(), Duration::from_secs(30));
+        time.advance(5);
+        assert_eq!(clock.elapsed(), Duration::from_secs(35));
+        clock.pause();
+        time.advance(5);
+        clock.resume();
+        clock.stop();
+        time.advance(5);
+        assert_eq!(clock.elapsed(), Duration::from_secs(35));
+    }
+
+    #[test]
+    fn reset_clock_is_not_started() {
+        let (mut clock, time) = fake_clock();
+        clock.start();
+        time.advance(3);
+        clock.stop();
+        clock.reset();
+        assert!(!clock.is_started());
+        assert!(!clock.is_stopped());
+        time.advance(3);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+    }
+}
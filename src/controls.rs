@@ -0,0 +1,640 @@
+/**This file contains input mapping layer that turns sdl events into game actions,
+ * the keyboard cursor and the bindings that are stored in the settings
+ */
+use crate::clock::{SystemClock, TimeSource};
+use crate::grid::CellCoord;
+use crate::layout::ScreenLayout;
+use nalgebra::Vector2;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+///Everything that player can do, regardless of the device used
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Reveal,
+    Flag,
+    Chord,
+    Restart,
+    Pause,
+    Hint,
+    Settings,
+    ///Moves the camera by the given amount of pixels
+    Pan(Vector2<i32>),
+    ZoomIn,
+    ZoomOut,
+}
+
+///How far camera moves with one key press or one step of the wheel, in pixels
+pub const PAN_STEP: i32 = 40;
+///How far mouse has to move while the reveal button is held to start dragging the field
+pub const DRAG_THRESHOLD: i32 = 5;
+
+///Action produced by the input mapping layer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MappedInput {
+    pub action: GameAction,
+    ///Where mouse was when action happened, None if action came from keyboard
+    /// and should be applied to the tile under the keyboard cursor
+    pub mouse_position: Option<Vector2<i32>>,
+}
+
+///Key bindings for every action, keys are stored using SDL key names(ex. "Up", "W", "F2", "Space")
+/// so that config file can be edited by hand
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub reveal: Vec<String>,
+    pub flag: Vec<String>,
+    pub chord: Vec<String>,
+    pub restart: Vec<String>,
+    pub pause: Vec<String>,
+    pub hint: Vec<String>,
+    pub settings: Vec<String>,
+    pub pan_up: Vec<String>,
+    pub pan_down: Vec<String>,
+    pub pan_left: Vec<String>,
+    pub pan_right: Vec<String>,
+    pub zoom_in: Vec<String>,
+    pub zoom_out: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            //arrows, wasd and vim keys all move the cursor
+            up: keys(&["Up", "W", "K"]),
+            down: keys(&["Down", "S", "J"]),
+            left: keys(&["Left", "A", "H"]),
+            right: keys(&["Right", "D", "L"]),
+            reveal: keys(&["Space", "Return"]),
+            flag: keys(&["F"]),
+            chord: keys(&["C"]),
+            //same as in the windows version
+            restart: keys(&["F2"]),
+            pause: keys(&["P", "Escape"]),
+            hint: keys(&["F1"]),
+            settings: keys(&["O"]),
+            pan_up: keys(&["Keypad 8", "PageUp"]),
+            pan_down: keys(&["Keypad 2", "PageDown"]),
+            pan_left: keys(&["Keypad 4", "Home"]),
+            pan_right: keys(&["Keypad 6", "End"]),
+            zoom_in: keys(&["=", "Keypad +"]),
+            zoom_out: keys(&["-", "Keypad -"]),
+        }
+    }
+}
+
+impl KeyBindings {
+    fn all_names(&self) -> impl Iterator<Item = &String> {
+        self.up
+            .iter()
+            .chain(self.down.iter())
+            .chain(self.left.iter())
+            .chain(self.right.iter())
+            .chain(self.reveal.iter())
+            .chain(self.flag.iter())
+            .chain(self.chord.iter())
+            .chain(self.restart.iter())
+            .chain(self.pause.iter())
+            .chain(self.hint.iter())
+            .chain(self.settings.iter())
+            .chain(self.pan_up.iter())
+            .chain(self.pan_down.iter())
+            .chain(self.pan_left.iter())
+            .chain(self.pan_right.iter())
+            .chain(self.zoom_in.iter())
+            .chain(self.zoom_out.iter())
+    }
+
+    ///Returns action bound to the key if there is one
+    pub fn action_for(&self, key: Keycode) -> Option<GameAction> {
+        let bound = |names: &Vec<String>| {
+            names
+                .iter()
+                .any(|name| Keycode::from_name(name) == Some(key))
+        };
+        [
+            (&self.up, GameAction::MoveUp),
+            (&self.down, GameAction::MoveDown),
+            (&self.left, GameAction::MoveLeft),
+            (&self.right, GameAction::MoveRight),
+            (&self.reveal, GameAction::Reveal),
+            (&self.flag, GameAction::Flag),
+            (&self.chord, GameAction::Chord),
+            (&self.restart, GameAction::Restart),
+            (&self.pause, GameAction::Pause),
+            (&self.hint, GameAction::Hint),
+            (&self.settings, GameAction::Settings),
+            (&self.pan_up, GameAction::Pan(Vector2::new(0, -PAN_STEP))),
+            (&self.pan_down, GameAction::Pan(Vector2::new(0, PAN_STEP))),
+            (&self.pan_left, GameAction::Pan(Vector2::new(-PAN_STEP, 0))),
+            (&self.pan_right, GameAction::Pan(Vector2::new(PAN_STEP, 0))),
+            (&self.zoom_in, GameAction::ZoomIn),
+            (&self.zoom_out, GameAction::ZoomOut),
+        ]
+        .into_iter()
+        .find(|(names, _)| bound(names))
+        .map(|(_, action)| action)
+    }
+}
+
+///Mouse buttons that can be used in the config file
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MouseBinding {
+    Left,
+    Middle,
+    Right,
+    X1,
+    X2,
+}
+
+impl MouseBinding {
+    fn matches(&self, button: MouseButton) -> bool {
+        matches!(
+            (self, button),
+            (MouseBinding::Left, MouseButton::Left)
+                | (MouseBinding::Middle, MouseButton::Middle)
+                | (MouseBinding::Right, MouseButton::Right)
+                | (MouseBinding::X1, MouseButton::X1)
+                | (MouseBinding::X2, MouseButton::X2)
+        )
+    }
+}
+
+///Mouse bindings for actions that make sense to do with the mouse
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MouseBindings {
+    pub reveal: Vec<MouseBinding>,
+    pub flag: Vec<MouseBinding>,
+    pub chord: Vec<MouseBinding>,
+    pub hint: Vec<MouseBinding>,
+}
+
+impl Default for MouseBindings {
+    fn default() -> Self {
+        Self {
+            reveal: vec![MouseBinding::Left],
+            flag: vec![MouseBinding::Right],
+            chord: vec![MouseBinding::Middle],
+            hint: Vec::new(),
+        }
+    }
+}
+
+impl MouseBindings {
+    ///Returns action bound to the button if there is one
+    pub fn action_for(&self, button: MouseButton) -> Option<GameAction> {
+        [
+            (&self.reveal, GameAction::Reveal),
+            (&self.flag, GameAction::Flag),
+            (&self.chord, GameAction::Chord),
+            (&self.hint, GameAction::Hint),
+        ]
+        .into_iter()
+        .find(|(bindings, _)| bindings.iter().any(|binding| binding.matches(button)))
+        .map(|(_, action)| action)
+    }
+}
+
+///Key and mouse bindings, stored as a part of the settings
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct ControlsConfig {
+    pub keys: KeyBindings,
+    pub mouse: MouseBindings,
+    ///Swaps left and right mouse buttons for left handed players
+    pub swap_mouse_buttons: bool,
+    ///If set, holding reveal button for this many milliseconds will place a flag instead
+    pub long_press_flag_ms: Option<u64>,
+}
+
+impl ControlsConfig {
+    ///Checks that all key names are known to SDL,
+    /// so that typos don't silently leave action without a key
+    pub fn validate(&self) -> Result<(), String> {
+        for name in self.keys.all_names() {
+            if Keycode::from_name(name).is_none() {
+                return Err(format!("Unknown key \"{}\"", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+///Reveal button press that might turn into a flag if held long enough,
+/// or into dragging of the field if mouse moves far enough
+struct PendingPress {
+    button: MouseButton,
+    position: Vector2<i32>,
+    started: Instant,
+    dragging: bool,
+}
+
+///Translates sdl events into game actions using the controls config.
+/// Time of the presses is taken from the time source, so that tests can control it
+pub struct InputMapper<T: TimeSource = SystemClock> {
+    pub config: ControlsConfig,
+    source: T,
+    pending_press: Option<PendingPress>,
+    ///Mouse wheel zooms instead of scrolling while control is held
+    control_held: bool,
+    ///Wheel events don't say where the mouse is, so it's remembered from the motion events
+    mouse_position: Vector2<i32>,
+}
+
+impl InputMapper<SystemClock> {
+    pub fn new(config: ControlsConfig) -> Self {
+        InputMapper::with_time_source(config, SystemClock)
+    }
+}
+
+impl<T: TimeSource> InputMapper<T> {
+    pub fn with_time_source(config: ControlsConfig, source: T) -> Self {
+        Self {
+            config,
+            source,
+            pending_press: None,
+            control_held: false,
+            mouse_position: Vector2::new(0, 0),
+        }
+    }
+
+    fn long_press_duration(&self) -> Option<Duration> {
+        self.config.long_press_flag_ms.map(Duration::from_millis)
+    }
+
+    fn physical_to_logical(&self, button: MouseButton) -> MouseButton {
+        if !self.config.swap_mouse_buttons {
+            return button;
+        }
+        match button {
+            MouseButton::Left => MouseButton::Right,
+            MouseButton::Right => MouseButton::Left,
+            other => other,
+        }
+    }
+
+    ///Returns action that the event represents, if there is any
+    pub fn map_event(&mut self, event: &Event) -> Option<MappedInput> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(key),
+                repeat,
+                ..
+            } => {
+                if matches!(key, Keycode::LCtrl | Keycode::RCtrl) {
+                    self.control_held = true;
+                }
+                let action = self.config.keys.action_for(*key)?;
+                let is_movement = matches!(
+                    action,
+                    GameAction::MoveUp
+                        | GameAction::MoveDown
+                        | GameAction::MoveLeft
+                        | GameAction::MoveRight
+                        | GameAction::Pan(_)
+                        | GameAction::ZoomIn
+                        | GameAction::ZoomOut
+                );
+                //holding the key should only repeat cursor movement, not spam flags
+                if *repeat && !is_movement {
+                    return None;
+                }
+                Some(MappedInput {
+                    action,
+                    mouse_position: None,
+                })
+            }
+            Event::MouseButtonDown {
+                x, y, mouse_btn, ..
+            } => {
+                let button = self.physical_to_logical(*mouse_btn);
+                let action = self.config.mouse.action_for(button)?;
+                let position = Vector2::new(*x, *y);
+                if action == GameAction::Reveal {
+                    //we don't know yet if this is a click, a long press or a drag
+                    self.pending_press = Some(PendingPress {
+                        button: *mouse_btn,
+                        position,
+                        started: self.source.now(),
+                        dragging: false,
+                    });
+                    return None;
+                }
+                Some(MappedInput {
+                    action,
+                    mouse_position: Some(position),
+                })
+            }
+            Event::MouseButtonUp { mouse_btn, .. } => {
+                let press = self.pending_press.take()?;
+                if press.button != *mouse_btn {
+                    self.pending_press = Some(press);
+                    return None;
+                }
+                //press that moved the mouse became dragging of the field, releasing it only ends the drag
+                if press.dragging {
+                    return None;
+                }
+                Some(MappedInput {
+                    action: GameAction::Reveal,
+                    mouse_position: Some(press.position),
+                })
+            }
+            Event::MouseMotion { x, y, .. } => {
+                let position = Vector2::new(*x, *y);
+                self.mouse_position = position;
+                let press = self.pending_press.as_mut()?;
+                let moved = position - press.position;
+                if !press.dragging && moved.x.abs().max(moved.y.abs()) < DRAG_THRESHOLD {
+                    return None;
+                }
+                press.dragging = true;
+                press.position = position;
+                //field follows the mouse, so camera moves the other way
+                Some(MappedInput {
+                    action: GameAction::Pan(-moved),
+                    mouse_position: None,
+                })
+            }
+            Event::MouseWheel { x, y, .. } => {
+                if self.control_held {
+                    let action = match y.signum() {
+                        1 => GameAction::ZoomIn,
+                        -1 => GameAction::ZoomOut,
+                        _ => return None,
+                    };
+                    return Some(MappedInput {
+                        action,
+                        mouse_position: Some(self.mouse_position),
+                    });
+                }
+                //wheel up moves the view up, which means camera goes up
+                Some(MappedInput {
+                    action: GameAction::Pan(Vector2::new(*x * PAN_STEP, -*y * PAN_STEP)),
+                    mouse_position: None,
+                })
+            }
+            Event::KeyUp {
+                keycode: Some(Keycode::LCtrl | Keycode::RCtrl),
+                ..
+            } => {
+                self.control_held = false;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    ///Checks for actions that depend on time rather than on events, should be called every frame
+    pub fn update(&mut self) -> Option<MappedInput> {
+        let duration = self.long_press_duration()?;
+        let press = self.pending_press.as_ref()?;
+        let held = self.source.now().saturating_duration_since(press.started);
+        if press.dragging || held < duration {
+            return None;
+        }
+        //press that was held long enough becomes a flag right away and is forgotten,
+        //so releasing the button afterwards does not reveal the tile
+        let press = self.pending_press.take()?;
+        Some(MappedInput {
+            action: GameAction::Flag,
+            mouse_position: Some(press.position),
+        })
+    }
+}
+
+///Tile that is currently selected using keyboard
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KeyboardCursor {
+    pub position: CellCoord,
+}
+
+impl KeyboardCursor {
+    ///Moves cursor by the given amount of tiles, cursor stops at the border of the field
+    pub fn move_by(&mut self, columns: i32, rows: i32, width: usize, height: usize) {
+        let clamp = |value: usize, offset: i32, size: usize| {
+            (value as i32 + offset).clamp(0, size as i32 - 1) as usize
+        };
+        self.position.col = clamp(self.position.col, columns, width);
+        self.position.row = clamp(self.position.row, rows, height);
+    }
+
+    ///Center of the selected tile on screen
+    pub fn screen_position(&self, layout: &ScreenLayout) -> Vector2<i32> {
+        layout.tile_center(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::keyboard::Mod;
+    use sdl2::mouse::MouseState;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    ///Time source that only moves when the test moves it
+    #[derive(Clone)]
+    struct FakeTime(Rc<Cell<Instant>>);
+
+    impl FakeTime {
+        fn advance(&self, milliseconds: u64) {
+            self.0
+                .set(self.0.get() + Duration::from_millis(milliseconds));
+        }
+    }
+
+    impl TimeSource for FakeTime {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    fn mapper(config: ControlsConfig) -> (InputMapper<FakeTime>, FakeTime) {
+        let time = FakeTime(Rc::new(Cell::new(Instant::now())));
+        (InputMapper::with_time_source(config, time.clone()), time)
+    }
+
+    fn press(button: MouseButton, x: i32, y: i32) -> Event {
+        Event::MouseButtonDown {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn: button,
+            clicks: 1,
+            x,
+            y,
+        }
+    }
+
+    fn release(button: MouseButton, x: i32, y: i32) -> Event {
+        Event::MouseButtonUp {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mouse_btn: button,
+            clicks: 1,
+            x,
+            y,
+        }
+    }
+
+    fn motion(x: i32, y: i32) -> Event {
+        Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: MouseState::from_sdl_state(0),
+            x,
+            y,
+            xrel: 0,
+            yrel: 0,
+        }
+    }
+
+    fn key(keycode: Keycode, repeat: bool) -> Event {
+        Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat,
+        }
+    }
+
+    fn at(action: GameAction, x: i32, y: i32) -> Option<MappedInput> {
+        Some(MappedInput {
+            action,
+            mouse_position: Some(Vector2::new(x, y)),
+        })
+    }
+
+    #[test]
+    fn swapped_buttons_reveal_with_the_right_one() {
+        let (mut mapper, _) = mapper(ControlsConfig {
+            swap_mouse_buttons: true,
+            ..ControlsConfig::default()
+        });
+        //reveal waits for the release, it could still become a drag
+        assert_eq!(mapper.map_event(&press(MouseButton::Right, 10, 20)), None);
+        assert_eq!(
+            mapper.map_event(&release(MouseButton::Right, 10, 20)),
+            at(GameAction::Reveal, 10, 20)
+        );
+        assert_eq!(
+            mapper.map_event(&press(MouseButton::Left, 30, 40)),
+            at(GameAction::Flag, 30, 40)
+        );
+        assert_eq!(mapper.map_event(&release(MouseButton::Left, 30, 40)), None);
+    }
+
+    #[test]
+    fn long_press_places_a_flag_instead_of_revealing() {
+        let (mut mapper, time) = mapper(ControlsConfig {
+            long_press_flag_ms: Some(500),
+            ..ControlsConfig::default()
+        });
+        assert_eq!(mapper.map_event(&press(MouseButton::Left, 10, 20)), None);
+        time.advance(499);
+        assert_eq!(mapper.update(), None);
+        time.advance(1);
+        assert_eq!(mapper.update(), at(GameAction::Flag, 10, 20));
+        assert_eq!(mapper.update(), None);
+        assert_eq!(mapper.map_event(&release(MouseButton::Left, 10, 20)), None);
+
+        //short press is still a reveal
+        assert_eq!(mapper.map_event(&press(MouseButton::Left, 50, 60)), None);
+        time.advance(200);
+        assert_eq!(mapper.update(), None);
+        assert_eq!(
+            mapper.map_event(&release(MouseButton::Left, 50, 60)),
+            at(GameAction::Reveal, 50, 60)
+        );
+    }
+
+    #[test]
+    fn press_is_never_a_flag_without_long_press() {
+        let (mut mapper, time) = mapper(ControlsConfig::default());
+        assert_eq!(mapper.map_event(&press(MouseButton::Left, 10, 20)), None);
+        time.advance(60_000);
+        assert_eq!(mapper.update(), None);
+        assert_eq!(
+            mapper.map_event(&release(MouseButton::Left, 10, 20)),
+            at(GameAction::Reveal, 10, 20)
+        );
+    }
+
+    #[test]
+    fn dragging_pans_the_field_and_cancels_the_reveal() {
+        let (mut mapper, time) = mapper(ControlsConfig {
+            long_press_flag_ms: Some(500),
+            ..ControlsConfig::default()
+        });
+        assert_eq!(mapper.map_event(&press(MouseButton::Left, 10, 20)), None);
+        //small shake of the hand is not a drag
+        assert_eq!(mapper.map_event(&motion(12, 21)), None);
+        let pan = |x: i32, y: i32| {
+            Some(MappedInput {
+                action: GameAction::Pan(Vector2::new(x, y)),
+                mouse_position: None,
+            })
+        };
+        assert_eq!(mapper.map_event(&motion(30, 20)), pan(-20, 0));
+        //once dragging, every move pans
+        assert_eq!(mapper.map_event(&motion(31, 22)), pan(-1, -2));
+        //dragging is not holding still, so it does not become a flag
+        time.advance(1000);
+        assert_eq!(mapper.update(), None);
+        assert_eq!(mapper.map_event(&release(MouseButton::Left, 31, 22)), None);
+        assert_eq!(mapper.map_event(&motion(60, 60)), None);
+    }
+
+    #[test]
+    fn keys_move_the_keyboard_cursor() {
+        let (mut mapper, _) = mapper(ControlsConfig::default());
+        let keyboard = |action: GameAction| {
+            Some(MappedInput {
+                action,
+                mouse_position: None,
+            })
+        };
+        assert_eq!(
+            mapper.map_event(&key(Keycode::W, false)),
+            keyboard(GameAction::MoveUp)
+        );
+        //holding the key keeps moving the cursor, but does not place more flags
+        assert_eq!(
+            mapper.map_event(&key(Keycode::D, true)),
+            keyboard(GameAction::MoveRight)
+        );
+        assert_eq!(
+            mapper.map_event(&key(Keycode::F, false)),
+            keyboard(GameAction::Flag)
+        );
+        assert_eq!(mapper.map_event(&key(Keycode::F, true)), None);
+
+        let mut cursor = KeyboardCursor::default();
+        cursor.move_by(0, -1, 5, 4);
+        assert_eq!(cursor.position, CellCoord::new(0, 0));
+        cursor.move_by(3, 2, 5, 4);
+        assert_eq!(cursor.position, CellCoord::new(3, 2));
+        //cursor stops at the border of the field
+        cursor.move_by(5, 5, 5, 4);
+        assert_eq!(cursor.position, CellCoord::new(4, 3));
+    }
+}
@@ -0,0 +1,697 @@
+/**This file contains co-op games over the network: players share one field that is kept by the server.
+ * Players only send what they want to do, server makes the move and sends the changed tiles to everyone,
+ * so everyone sees the same field. Mines stay on the server until they are revealed.
+ * When two players act on the same tile at once, whoever got to the server first wins:
+ * every change of the field gets the next revision and players send the last revision they have seen with every action,
+ * if another player changed the tile after that revision the action is rejected, because player has not seen the change.
+ * Flags and question marks belong to the player who placed them, other players can't remove them
+ */
+use crate::events::GameEvent;
+use crate::gameplay::{
+    board_layout, check_mines, chord_block, empty_grid, flag_block, make_first_click_safe,
+    reveal_block, Board,
+};
+use crate::grid::{CellCoord, Grid};
+use crate::network::{
+    self, accept_players, broadcast, listen, CellState, Connection, CoopAction, GameMode, Message,
+};
+use crate::rules::GameRules;
+use crate::save::BoardLayout;
+use crate::Field;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::net::TcpListener;
+use std::time::Duration;
+
+///How long bot waits between its moves
+const BOT_MOVE_DELAY: Duration = Duration::from_millis(300);
+
+///Tiles other players are pointing at, board renderer colors them with the color of the player
+#[derive(Clone, Debug, Default)]
+pub struct PlayerCursors {
+    pub cursors: Vec<Option<CellCoord>>,
+}
+
+///Field of the co-op game as it's kept by the server
+pub struct CoopServer {
+    pub board: Board,
+    rules: GameRules,
+    ///Revision of the last change of every tile and the player who made it
+    changed_at: Grid<(u64, usize)>,
+    ///Revision of the last change of the field
+    pub revision: u64,
+}
+
+impl CoopServer {
+    pub fn new(board: Board, rules: GameRules) -> Self {
+        Self {
+            changed_at: board.grid.filled_like((0, 0)),
+            board,
+            rules,
+            revision: 0,
+        }
+    }
+
+    ///Tile as players see it
+    fn cell_state(&self, cell: CellCoord) -> CellState {
+        let field = &self.board.grid[cell];
+        CellState {
+            position: [cell.row, cell.col],
+            revealed: field.revealed,
+            value: if field.revealed { field.value } else { 0 },
+            bombs: if field.revealed { field.bombs } else { 0 },
+            flags: field.flags,
+            questioned: field.questioned,
+            owner: field.owner,
+        }
+    }
+
+    ///Makes the action of the player if it does not conflict with the actions of others.
+    /// Returns the tiles that have changed or the reason why action was rejected
+    pub fn act(
+        &mut self,
+        player: usize,
+        action: CoopAction,
+        cell: CellCoord,
+        revision: u64,
+    ) -> Result<Vec<CellState>, String> {
+        if self.board.result.is_some() {
+            return Err("Game is over".to_owned());
+        }
+        let field = match self.board.grid.get(cell) {
+            Some(field) => field.clone(),
+            None => return Err("Tile is outside of the field".to_owned()),
+        };
+        //chording depends on the flags around the tile, so they have to be the ones player saw too
+        let mut depends_on = vec![cell];
+        if action == CoopAction::Chord {
+            depends_on.extend(self.board.grid.neighbors(cell));
+        }
+        let changed_by_others = |cell: &CellCoord| {
+            let (changed_at, changed_by) = self.changed_at[*cell];
+            changed_at > revision && changed_by != player
+        };
+        if depends_on.iter().any(changed_by_others) {
+            return Err("Someone else has changed this tile first".to_owned());
+        }
+        let board = &mut self.board;
+        let mut changed: Vec<CellCoord> = Vec::new();
+        let mut mine_hit = false;
+        match action {
+            CoopAction::Reveal => {
+                if field.is_flagged() {
+                    return Err("Tile is flagged".to_owned());
+                }
+                if self.rules.safe_first_click {
+                    make_first_click_safe(cell, &mut board.grid, board.mines_per_cell);
+                }
+                reveal_block(cell, &mut board.grid, &mut changed);
+                mine_hit = board.grid[cell].has_bomb();
+            }
+            CoopAction::Flag => {
+                if let Some(owner) = field.owner.filter(|owner| *owner != player) {
+                    return Err(format!("Tile was marked by player {}", owner + 1));
+                }
+                if flag_block(
+                    cell,
+                    &mut board.grid,
+                    &mut board.flag_count,
+                    board.total_mine_count as i32,
+                    board.mines_per_cell,
+                    &self.rules,
+                ) {
+                    let field = &mut board.grid[cell];
+                    field.owner = if field.is_flagged() || field.questioned {
+                        Some(player)
+                    } else {
+                        None
+                    };
+                    changed.push(cell);
+                }
+            }
+            CoopAction::Chord => {
+                mine_hit = !chord_block(cell, &mut board.grid, &mut changed).is_empty()
+            }
+        }
+        //same rules as in the single player game: mine loses it and flags on every mine win it
+        if mine_hit {
+            board.mine_hit = true;
+            board.result = Some(false);
+            let bombs: Vec<CellCoord> = board
+                .grid
+                .iter()
+                .filter(|(_, field)| field.has_bomb() && !field.revealed)
+                .map(|(cell, _)| cell)
+                .collect();
+            for bomb in bombs {
+                board.grid[bomb].revealed = true;
+                changed.push(bomb);
+            }
+        } else if action == CoopAction::Flag && check_mines(&board.grid) {
+            board.result = Some(true);
+        }
+        if !changed.is_empty() {
+            self.revision += 1;
+            for cell in changed.iter() {
+                self.changed_at[*cell] = (self.revision, player);
+            }
+        }
+        Ok(changed
+            .into_iter()
+            .map(|cell| self.cell_state(cell))
+            .collect())
+    }
+}
+
+///Starts the co-op server on a separate thread. Server waits until `players` players join
+/// and then makes their moves on the board until the game ends
+pub fn host(port: u16, players: usize, board: Board, rules: GameRules) -> Result<(), String> {
+    let listener = listen(port)?;
+    println!(
+        "Hosting co-op game for {} players on port {}",
+        players, port
+    );
+    std::thread::spawn(move || {
+        if let Err(e) = serve(listener, players, CoopServer::new(board, rules)) {
+            println!("Co-op server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+///Runs the co-op server without playing on it, returns once the game ends
+pub fn run_server(port: u16, players: usize, board: Board, rules: GameRules) -> Result<(), String> {
+    let listener = listen(port)?;
+    println!(
+        "Co-op server for {} players is running on port {}",
+        players, port
+    );
+    serve(listener, players, CoopServer::new(board, rules))
+}
+
+fn serve(listener: TcpListener, players: usize, mut server: CoopServer) -> Result<(), String> {
+    let (mut connections, names) = accept_players(&listener, players, GameMode::Coop)?;
+    broadcast(
+        &mut connections,
+        &Message::CoopStart {
+            //mines are kept secret, players only learn about them when they get revealed
+            board: BoardLayout {
+                mines: Vec::new(),
+                ..board_layout(&server.board)
+            },
+            mine_count: server.board.total_mine_count,
+            players: names,
+        },
+    );
+    while server.board.result.is_none() && connections.iter().any(|c| c.is_some()) {
+        for player in 0..players {
+            let message = match &connections[player] {
+                Some(connection) => connection.try_receive(),
+                None => None,
+            };
+            match message {
+                None => {}
+                Some(Ok(Message::Act {
+                    action,
+                    position,
+                    revision,
+                })) => {
+                    match server.act(player, action, CellCoord::from_row_col(position), revision) {
+                        Ok(cells) if cells.is_empty() => {}
+                        Ok(cells) => broadcast(
+                            &mut connections,
+                            &Message::Cells {
+                                revision: server.revision,
+                                cells,
+                            },
+                        ),
+                        Err(reason) => {
+                            if let Some(connection) = &mut connections[player] {
+                                let _ = connection.send(&Message::Rejected { position, reason });
+                            }
+                        }
+                    }
+                    if let Some(won) = server.board.result {
+                        broadcast(&mut connections, &Message::GameOver { won });
+                        println!("Co-op game is {}", if won { "won" } else { "lost" });
+                        //actions of the other players came too late
+                        break;
+                    }
+                }
+                Some(Ok(Message::Cursor { position })) => broadcast(
+                    &mut connections,
+                    &Message::PlayerCursor { player, position },
+                ),
+                Some(result) => {
+                    //connection was closed or player sent something that can't be understood
+                    let reason = match result {
+                        Ok(message) => format!("Unexpected message {:?}", message),
+                        Err(e) => e,
+                    };
+                    if let Some(connection) = &mut connections[player] {
+                        connection.reject(&reason);
+                    }
+                    connections[player] = None;
+                    broadcast(&mut connections, &Message::PlayerLeft { player });
+                }
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
+
+///Player's side of the co-op game: sends the actions of the player and copies changes of the field to the board
+pub struct CoopClient {
+    connection: Option<Connection>,
+    ///Index of the local player
+    pub player: usize,
+    pub names: Vec<String>,
+    ///Players that have disconnected
+    pub left: Vec<bool>,
+    ///Field of the game without the mines
+    pub board: BoardLayout,
+    pub mine_count: u32,
+    ///Last revision of the field that was received
+    revision: u64,
+    ///Tiles other players are pointing at
+    pub cursors: Vec<Option<CellCoord>>,
+    ///Last cursor position that was sent, so that it's only sent when it changes
+    sent_cursor: Option<CellCoord>,
+    ///Some(true) if players won and Some(false) if they lost
+    pub result: Option<bool>,
+}
+
+impl CoopClient {
+    ///Connects to the server and waits until everyone has joined and the game starts
+    pub fn join(address: &str, name: &str) -> Result<CoopClient, String> {
+        let (connection, player) = network::join(address, name, GameMode::Coop)?;
+        let (board, mine_count, names) = match connection.receive()? {
+            Message::CoopStart {
+                board,
+                mine_count,
+                players,
+            } => (board, mine_count, players),
+            Message::Error { reason } => {
+                return Err(format!("Server stopped the game: {}", reason))
+            }
+            message => return Err(format!("Expected co-op start, got {:?}", message)),
+        };
+        board.validate("the co-op game")?;
+        Ok(CoopClient {
+            connection: Some(connection),
+            player,
+            left: vec![false; names.len()],
+            cursors: vec![None; names.len()],
+            names,
+            board,
+            mine_count,
+            revision: 0,
+            sent_cursor: None,
+            result: None,
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    fn send(&mut self, message: &Message) {
+        if let Some(connection) = &mut self.connection {
+            if let Err(e) = connection.send(message) {
+                println!("{}", e);
+                self.connection = None;
+            }
+        }
+    }
+
+    ///Asks the server to make the action, field changes once the server answers
+    pub fn send_action(&mut self, action: CoopAction, cell: CellCoord) {
+        self.send(&Message::Act {
+            action,
+            position: [cell.row, cell.col],
+            revision: self.revision,
+        });
+    }
+
+    ///Lets others know where player is pointing
+    pub fn send_cursor(&mut self, cell: CellCoord) {
+        if self.sent_cursor != Some(cell) {
+            self.sent_cursor = Some(cell);
+            self.send(&Message::Cursor {
+                position: [cell.row, cell.col],
+            });
+        }
+    }
+
+    ///Copies the tile from the server to the board, returns events for the changes
+    fn apply(board: &mut Board, state: CellState, events: &mut Vec<GameEvent>) {
+        let cell = CellCoord::from_row_col(state.position);
+        let field = match board.grid.get_mut(cell) {
+            Some(field) => field,
+            None => return,
+        };
+        if state.revealed && !field.revealed {
+            events.push(GameEvent::CellRevealed(cell));
+        }
+        if state.flags > field.flags {
+            events.push(GameEvent::Flagged(cell));
+        } else if state.flags < field.flags {
+            events.push(GameEvent::Unflagged(cell));
+        }
+        board.flag_count += state.flags as i32 - field.flags as i32;
+        *field = Field {
+            value: state.value,
+            bombs: state.bombs,
+            border: false,
+            revealed: state.revealed,
+            flags: state.flags,
+            questioned: state.questioned,
+            owner: state.owner,
+        };
+    }
+
+    ///Reads messages from the server and changes the board to match the field on the server.
+    /// Returns events for everything that has changed, so that the rest of the game can react to them
+    pub fn update(&mut self, board: &mut Board) -> Vec<GameEvent> {
+        let mut events: Vec<GameEvent> = Vec::new();
+        while let Some(message) = self.connection.as_ref().and_then(|c| c.try_receive()) {
+            match message {
+                Ok(Message::Cells { revision, cells }) => {
+                    self.revision = revision;
+                    for state in cells {
+                        CoopClient::apply(board, state, &mut events);
+                    }
+                }
+                Ok(Message::Rejected { position, reason }) => {
+                    println!("Tile [{}, {}]: {}", position[0], position[1], reason)
+                }
+                Ok(Message::PlayerCursor { player, position }) => {
+                    if player != self.player {
+                        if let Some(cursor) = self.cursors.get_mut(player) {
+                            *cursor = Some(CellCoord::from_row_col(position));
+                        }
+                    }
+                }
+                Ok(Message::PlayerLeft { player }) => {
+                    println!("{} has left the game", self.name(player));
+                    if let Some(cursor) = self.cursors.get_mut(player) {
+                        *cursor = None;
+                    }
+                    if let Some(left) = self.left.get_mut(player) {
+                        *left = true;
+                    }
+                }
+                Ok(Message::GameOver { won }) => {
+                    self.result = Some(won);
+                    board.result = Some(won);
+                    board.mine_hit = !won;
+                    events.push(if won {
+                        GameEvent::GameWon
+                    } else {
+                        GameEvent::GameLost
+                    });
+                }
+                Ok(message) => println!("Unexpected message from the server: {:?}", message),
+                Err(e) => {
+                    //server closes the connections once the game is over
+                    if self.result.is_none() {
+                        println!("Lost connection to the server: {}", e);
+                    }
+                    self.connection = None;
+                }
+            }
+        }
+        events
+    }
+
+    fn name(&self, player: usize) -> &str {
+        self.names
+            .get(player)
+            .map(|name| name.as_str())
+            .unwrap_or("?")
+    }
+
+    ///Players of the game in a single line, it's shown in the title of the window
+    pub fn status_text(&self) -> String {
+        let players: Vec<String> = (0..self.names.len())
+            .map(|player| {
+                let you = if player == self.player { " (you)" } else { "" };
+                let left = if self.left[player] { " (left)" } else { "" };
+                format!("{}{}{}", self.name(player), you, left)
+            })
+            .collect();
+        let result = match self.result {
+            Some(true) => " - field cleared!",
+            Some(false) => " - mine hit!",
+            None => "",
+        };
+        format!("Co-op | {}{}", players.join(" | "), result)
+    }
+}
+
+///Picks the next move of the bot: flags tiles that have to be mines, chords tiles that have all
+/// of their flags and reveals a random tile if nothing is certain.
+/// Flags are only placed if the rules allow it, otherwise server would refuse the same move forever
+fn bot_move(
+    board: &Board,
+    player: usize,
+    rules: &GameRules,
+    rng: &mut StdRng,
+) -> Option<(CoopAction, CellCoord)> {
+    let grid = &board.grid;
+    let can_flag = rules.can_place_flag(board.flag_count, board.total_mine_count as i32);
+    for (cell, field) in grid.iter() {
+        if !field.revealed || field.has_bomb() {
+            continue;
+        }
+        let neighbors = grid.neighbors(cell);
+        //tiles around that could still get a flag from this player and how many flags they can take
+        let open: Vec<CellCoord> = neighbors
+            .iter()
+            .copied()
+            .filter(|n| {
+                let field = &grid[*n];
+                !field.revealed
+                    && !field.questioned
+                    && field.flags < board.mines_per_cell
+                    && field.owner.unwrap_or(player) == player
+            })
+            .collect();
+        if open.is_empty() {
+            continue;
+        }
+        let flags: i32 = neighbors.iter().map(|n| grid[*n].flags as i32).sum();
+        let room: i32 = open
+            .iter()
+            .map(|n| (board.mines_per_cell - grid[*n].flags) as i32)
+            .sum();
+        if flags == field.value {
+            if open.iter().any(|n| !grid[*n].is_flagged()) {
+                return Some((CoopAction::Chord, cell));
+            }
+        } else if can_flag && field.value - flags == room {
+            return Some((CoopAction::Flag, open[0]));
+        }
+    }
+    let hidden: Vec<CellCoord> = grid
+        .iter()
+        .filter(|(_, field)| !field.revealed && !field.is_flagged() && !field.questioned)
+        .map(|(cell, _)| cell)
+        .collect();
+    hidden.choose(rng).map(|cell| (CoopAction::Reveal, *cell))
+}
+
+///Joins the co-op game without a window and plays it with simple rules, used to try the server out
+pub fn run_bot(address: &str, name: &str, seed: Option<u64>) -> Result<(), String> {
+    let mut client = CoopClient::join(address, name)?;
+    let mut board = Board::new(
+        empty_grid(&client.board),
+        client.mine_count,
+        client.board.mines_per_cell,
+    );
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    //server does not send its rules, so bot never places more flags than there are mines,
+    //that is allowed by every server
+    let rules = GameRules {
+        allow_over_flagging: false,
+        ..GameRules::default()
+    };
+    println!("{}", client.status_text());
+    while client.result.is_none() && client.is_connected() {
+        let revealed = client
+            .update(&mut board)
+            .iter()
+            .filter(|event| matches!(event, GameEvent::CellRevealed(_)))
+            .count();
+        if revealed > 0 {
+            println!("{} tiles revealed", revealed);
+        }
+        if client.result.is_some() {
+            break;
+        }
+        if let Some((action, cell)) = bot_move(&board, client.player, &rules, &mut rng) {
+            client.send_cursor(cell);
+            client.send_action(action, cell);
+        }
+        std::thread::sleep(BOT_MOVE_DELAY);
+    }
+    println!("{}", client.status_text());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameplay::grid_from_layout;
+    use crate::grid::{CellShape, Topology};
+
+    ///4x4 field with mines in the top left and the bottom right corners
+    fn server() -> CoopServer {
+        let layout = BoardLayout {
+            width: 4,
+            height: 4,
+            shape: CellShape::Square,
+            topology: Topology::Bounded,
+            neighborhood: None,
+            mines_per_cell: 1,
+            mines: vec![[0, 0], [3, 3]],
+        };
+        let rules = GameRules {
+            allow_over_flagging: false,
+            question_marks: true,
+            safe_first_click: false,
+            win_on_clear: false,
+        };
+        CoopServer::new(Board::new(grid_from_layout(&layout), 2, 1), rules)
+    }
+
+    #[test]
+    fn action_on_a_tile_changed_after_the_seen_revision_is_rejected() {
+        let mut server = server();
+        let tile = CellCoord::new(1, 2);
+        server.act(0, CoopAction::Flag, tile, 0).unwrap();
+        assert_eq!(server.revision, 1);
+        //second player has not seen the flag yet
+        assert_eq!(
+            server.act(1, CoopAction::Flag, tile, 0),
+            Err("Someone else has changed this tile first".to_owned())
+        );
+        //chording looks at the flags around the tile, so it's rejected too
+        assert_eq!(
+            server.act(1, CoopAction::Chord, CellCoord::new(1, 1), 0),
+            Err("Someone else has changed this tile first".to_owned())
+        );
+        //player's own changes never get in the way
+        assert!(server.act(0, CoopAction::Flag, tile, 0).is_ok());
+        assert_eq!(server.revision, 2);
+        //tiles nobody touched can be changed with an old revision
+        assert!(server
+            .act(1, CoopAction::Flag, CellCoord::new(0, 3), 0)
+            .is_ok());
+    }
+
+    #[test]
+    fn flags_of_another_player_are_protected() {
+        let mut server = server();
+        let tile = CellCoord::new(0, 0);
+        server.act(0, CoopAction::Flag, tile, 0).unwrap();
+        let revision = server.revision;
+        assert_eq!(
+            server.act(1, CoopAction::Flag, tile, revision),
+            Err("Tile was marked by player 1".to_owned())
+        );
+        assert_eq!(
+            server.act(1, CoopAction::Reveal, tile, revision),
+            Err("Tile is flagged".to_owned())
+        );
+        assert_eq!(server.board.grid[tile].flags, 1);
+        //question mark belongs to the player too, tile is free again once it's cleared
+        let questioned = server.act(0, CoopAction::Flag, tile, revision).unwrap();
+        assert_eq!(questioned[0].owner, Some(0));
+        assert!(questioned[0].questioned);
+        assert!(server
+            .act(1, CoopAction::Flag, tile, server.revision)
+            .is_err());
+        let cleared = server
+            .act(0, CoopAction::Flag, tile, server.revision)
+            .unwrap();
+        assert_eq!(cleared[0].owner, None);
+        let flagged = server
+            .act(1, CoopAction::Flag, tile, server.revision)
+            .unwrap();
+        assert_eq!(flagged[0].owner, Some(1));
+    }
+
+    #[test]
+    fn hidden_mines_are_never_sent() {
+        let mut server = server();
+        let mines = [CellCoord::new(0, 0), CellCoord::new(3, 3)];
+        //empty corners without the mines open every safe tile
+        let mut sent = server
+            .act(0, CoopAction::Reveal, CellCoord::new(3, 0), 0)
+            .unwrap();
+        sent.extend(
+            server
+                .act(0, CoopAction::Reveal, CellCoord::new(0, 3), 0)
+                .unwrap(),
+        );
+        assert_eq!(sent.len(), 14);
+        sent.extend(
+            server
+                .act(1, CoopAction::Flag, mines[0], server.revision)
+                .unwrap(),
+        );
+        for state in sent {
+            if !state.revealed {
+                assert_eq!((state.value, state.bombs), (0, 0), "{:?}", state);
+            }
+            assert!(state.revealed || state.position == [0, 0], "{:?}", state);
+        }
+        for (cell, field) in server.board.grid.iter() {
+            let state = server.cell_state(cell);
+            assert_eq!(state.revealed, !field.has_bomb());
+            assert_eq!(state.bombs, 0);
+        }
+        //game is won by flagging the last mine, only then the server is done
+        assert!(server.board.result.is_none());
+        server
+            .act(1, CoopAction::Flag, mines[1], server.revision)
+            .unwrap();
+        assert_eq!(server.board.result, Some(true));
+    }
+
+    #[test]
+    fn bot_does_not_flag_past_the_mine_count() {
+        let layout = BoardLayout {
+            width: 5,
+            height: 1,
+            shape: CellShape::Square,
+            topology: Topology::Bounded,
+            neighborhood: None,
+            mines_per_cell: 1,
+            mines: vec![[0, 2]],
+        };
+        let mut board = Board::new(grid_from_layout(&layout), 1, 1);
+        board.grid[CellCoord::new(3, 0)].revealed = true;
+        board.grid[CellCoord::new(4, 0)].revealed = true;
+        let rules = server().rules;
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(
+            bot_move(&board, 0, &rules, &mut rng),
+            Some((CoopAction::Flag, CellCoord::new(2, 0)))
+        );
+        //wrong flag has used up the only flag, so the mine can't be flagged anymore
+        board.grid[CellCoord::new(0, 0)].flags = 1;
+        board.flag_count = 1;
+        for _ in 0..10 {
+            let (action, cell) = bot_move(&board, 0, &rules, &mut rng).unwrap();
+            assert_eq!(action, CoopAction::Reveal);
+            assert_ne!(cell, CellCoord::new(0, 0));
+        }
+    }
+}
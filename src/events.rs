@@ -0,0 +1,33 @@
+/**This file contains events that gameplay systems send when something happens in the game.
+ * Events are sent through the event channel resource, so anything that needs to react to them
+ * registers its own reader instead of being called from the gameplay code
+ */
+use crate::grid::CellCoord;
+use specs::shrev::EventChannel;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    ///Cell was revealed, one event is sent for every cell that flood reveal opened
+    CellRevealed(CellCoord),
+    Flagged(CellCoord),
+    ///Flag was removed, cell could have got a question mark instead
+    Unflagged(CellCoord),
+    ///Player revealed a mine, game is lost right after this unless players take turns
+    MineHit(CellCoord),
+    GameWon,
+    GameLost,
+    ///New field was made, this is sent on the start of the program and on every restart
+    GameStarted,
+    ///Whole amount of seconds shown by the game clock has changed
+    TimerTick(u64),
+    ///It's now turn of the player with this index, only sent when players take turns
+    TurnChanged(usize),
+    ///Score of the player has changed, only sent when players take turns
+    ScoreChanged {
+        player: usize,
+        score: i64,
+    },
+}
+
+///Channel through which gameplay systems send the events
+pub type GameEvents = EventChannel<GameEvent>;
@@ -0,0 +1,478 @@
+/**This file contains art that is drawn by the game itself.
+ * It's used in place of the textures that are missing, so that game can be played
+ * even if the art was not downloaded
+ */
+use crate::assets::{multi_mine_tile_states, TextureSource, Theme};
+use crate::options::{MAX_MINES_PER_CELL, MAX_TILE_VALUE};
+use crate::render::TextureAtlas;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::{Point, Rect};
+use sdl2::render::BlendMode;
+use sdl2::render::Canvas;
+use sdl2::surface::Surface;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+const TILE_SIZE: i32 = 16;
+const FACE_SIZE: i32 = 20;
+const DIGIT_WIDTH: i32 = 20;
+const DIGIT_HEIGHT: i32 = 36;
+///Where faces start in the atlas
+const FACES_Y: i32 = TILE_SIZE;
+///Where display digits start in the atlas
+const DIGITS_Y: i32 = FACES_Y + FACE_SIZE;
+///Where hex tiles start in the atlas
+const HEX_Y: i32 = DIGITS_Y + DIGIT_HEIGHT;
+///How many textures there are for a single shape of tiles,
+/// 14 of the original game and the ones for multi-mine fields
+const TILE_TEXTURE_COUNT: i32 = 14 + (MAX_TILE_VALUE - 8) + (MAX_MINES_PER_CELL as i32 - 1);
+
+///All tile textures of one shape are in a single row
+const ATLAS_WIDTH: u32 = (TILE_TEXTURE_COUNT * TILE_SIZE) as u32;
+const ATLAS_HEIGHT: u32 = (HEX_Y + TILE_SIZE) as u32;
+
+const LIGHT: Color = Color::RGB(255, 255, 255);
+const FACE_GREY: Color = Color::RGB(192, 192, 192);
+const SHADOW: Color = Color::RGB(128, 128, 128);
+
+///Tiny 3x5 font for numbers on tiles, '#' is a filled pixel
+const GLYPHS: [(char, [&str; 5]); 11] = [
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["##.", "..#", ".#.", "#..", "###"]),
+    ('3', ["##.", "..#", ".#.", "..#", "##."]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "##.", "..#", "##."]),
+    ('6', [".##", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", ".#.", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "##."]),
+    ('?', ["##.", "..#", ".#.", "...", ".#."]),
+];
+
+///Colors of the numbers, same as in the original game
+fn number_color(value: i32) -> Color {
+    match value {
+        1 => Color::RGB(0, 0, 255),
+        2 => Color::RGB(0, 128, 0),
+        3 => Color::RGB(255, 0, 0),
+        4 => Color::RGB(0, 0, 128),
+        5 => Color::RGB(128, 0, 0),
+        6 => Color::RGB(0, 128, 128),
+        7 => Color::RGB(0, 0, 0),
+        _ => Color::RGB(128, 128, 128),
+    }
+}
+
+///Which of the seven segments(a to g, clockwise from the top and then the middle one) are lit for each digit
+const SEGMENTS: [[bool; 7]; 10] = [
+    [true, true, true, true, true, true, false],
+    [false, true, true, false, false, false, false],
+    [true, true, false, true, true, false, true],
+    [true, true, true, true, false, false, true],
+    [false, true, true, false, false, true, true],
+    [true, false, true, true, false, true, true],
+    [true, false, true, true, true, true, true],
+    [true, true, true, false, false, false, false],
+    [true, true, true, true, true, true, true],
+    [true, true, true, true, false, true, true],
+];
+
+type AtlasCanvas = Canvas<Surface<'static>>;
+
+fn fill(
+    canvas: &mut AtlasCanvas,
+    color: Color,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+) -> Result<(), String> {
+    canvas.set_draw_color(color);
+    canvas.fill_rect(Rect::new(x, y, w, h))
+}
+
+///Draws covered tile with light top left edges and dark bottom right edges
+fn draw_raised_tile(canvas: &mut AtlasCanvas, x: i32, color: Color) -> Result<(), String> {
+    let size = TILE_SIZE as u32;
+    fill(canvas, SHADOW, x, 0, size, size)?;
+    fill(canvas, LIGHT, x, 0, size - 2, size - 2)?;
+    fill(canvas, color, x + 2, 2, size - 4, size - 4)
+}
+
+///Draws revealed tile, which is flat with a thin line on the top left
+fn draw_flat_tile(canvas: &mut AtlasCanvas, x: i32, color: Color) -> Result<(), String> {
+    let size = TILE_SIZE as u32;
+    fill(canvas, SHADOW, x, 0, size, size)?;
+    fill(canvas, color, x + 1, 1, size - 1, size - 1)
+}
+
+///Draws glyph with the top left corner at the given point, every pixel of the glyph is `scale` pixels big
+fn draw_glyph(
+    canvas: &mut AtlasCanvas,
+    left: i32,
+    top: i32,
+    glyph: char,
+    color: Color,
+    scale: i32,
+) -> Result<(), String> {
+    let rows = match GLYPHS.iter().find(|(c, _)| *c == glyph) {
+        Some((_, rows)) => rows,
+        None => return Ok(()),
+    };
+    for (row, line) in rows.iter().enumerate() {
+        for (column, pixel) in line.chars().enumerate() {
+            if pixel == '#' {
+                fill(
+                    canvas,
+                    color,
+                    left + column as i32 * scale,
+                    top + row as i32 * scale,
+                    scale as u32,
+                    scale as u32,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+///Draws text at 2x scale in the middle of the tile, there is only room for two glyphs
+fn draw_tile_text(
+    canvas: &mut AtlasCanvas,
+    x: i32,
+    text: &str,
+    color: Color,
+) -> Result<(), String> {
+    let count = text.chars().count() as i32;
+    //glyphs are 6 pixels wide with a pixel between them
+    let mut left = x + (TILE_SIZE - (count * 7 - 1)) / 2;
+    for glyph in text.chars() {
+        draw_glyph(canvas, left, (TILE_SIZE - 10) / 2, glyph, color, 2)?;
+        left += 7;
+    }
+    Ok(())
+}
+
+///Draws filled circle by drawing a line for every row of pixels
+fn draw_circle(
+    canvas: &mut AtlasCanvas,
+    center: Point,
+    radius: i32,
+    color: Color,
+) -> Result<(), String> {
+    canvas.set_draw_color(color);
+    for dy in -radius..=radius {
+        let half = (((radius * radius - dy * dy) as f32).sqrt()) as i32;
+        canvas.draw_line(
+            Point::new(center.x - half, center.y + dy),
+            Point::new(center.x + half, center.y + dy),
+        )?;
+    }
+    Ok(())
+}
+
+fn draw_bomb(canvas: &mut AtlasCanvas, x: i32) -> Result<(), String> {
+    draw_flat_tile(canvas, x, Color::RGB(255, 0, 0))?;
+    let center = Point::new(x + TILE_SIZE / 2, TILE_SIZE / 2);
+    canvas.set_draw_color(Color::BLACK);
+    canvas.draw_line(
+        Point::new(center.x - 6, center.y),
+        Point::new(center.x + 6, center.y),
+    )?;
+    canvas.draw_line(
+        Point::new(center.x, center.y - 6),
+        Point::new(center.x, center.y + 6),
+    )?;
+    draw_circle(canvas, center, 4, Color::BLACK)?;
+    fill(canvas, LIGHT, center.x - 2, center.y - 2, 2, 2)
+}
+
+fn draw_flag(canvas: &mut AtlasCanvas, x: i32) -> Result<(), String> {
+    draw_raised_tile(canvas, x, FACE_GREY)?;
+    //pole and the base
+    fill(canvas, Color::BLACK, x + 8, 4, 1, 8)?;
+    fill(canvas, Color::BLACK, x + 5, 11, 7, 2)?;
+    //flag itself is a triangle pointing to the left
+    canvas.set_draw_color(Color::RED);
+    for i in 0..4 {
+        canvas.draw_line(Point::new(x + 8 - (4 - i), 3 + i), Point::new(x + 8, 3 + i))?;
+        canvas.draw_line(Point::new(x + 8 - i, 7 + i), Point::new(x + 8, 7 + i))?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mood {
+    Default,
+    Loose,
+    Win,
+    Hover,
+}
+
+fn draw_face(canvas: &mut AtlasCanvas, x: i32, mood: Mood) -> Result<(), String> {
+    fill(
+        canvas,
+        SHADOW,
+        x,
+        FACES_Y,
+        FACE_SIZE as u32,
+        FACE_SIZE as u32,
+    )?;
+    fill(
+        canvas,
+        FACE_GREY,
+        x + 1,
+        FACES_Y + 1,
+        FACE_SIZE as u32 - 2,
+        FACE_SIZE as u32 - 2,
+    )?;
+    let center = Point::new(x + FACE_SIZE / 2, FACES_Y + FACE_SIZE / 2);
+    draw_circle(canvas, center, 8, Color::BLACK)?;
+    draw_circle(canvas, center, 7, Color::RGB(255, 255, 0))?;
+    let eye_y = center.y - 3;
+    match mood {
+        Mood::Loose => {
+            //crossed out eyes
+            canvas.set_draw_color(Color::BLACK);
+            for eye_x in [center.x - 3, center.x + 3] {
+                canvas.draw_line(
+                    Point::new(eye_x - 1, eye_y - 1),
+                    Point::new(eye_x + 1, eye_y + 1),
+                )?;
+                canvas.draw_line(
+                    Point::new(eye_x - 1, eye_y + 1),
+                    Point::new(eye_x + 1, eye_y - 1),
+                )?;
+            }
+        }
+        Mood::Win => {
+            //sunglasses
+            fill(canvas, Color::BLACK, center.x - 6, eye_y - 1, 13, 1)?;
+            fill(canvas, Color::BLACK, center.x - 5, eye_y, 4, 2)?;
+            fill(canvas, Color::BLACK, center.x + 2, eye_y, 4, 2)?;
+        }
+        _ => {
+            fill(canvas, Color::BLACK, center.x - 3, eye_y, 2, 2)?;
+            fill(canvas, Color::BLACK, center.x + 2, eye_y, 2, 2)?;
+        }
+    }
+    let mouth_y = center.y + 3;
+    match mood {
+        Mood::Loose => {
+            //frown
+            fill(canvas, Color::BLACK, center.x - 2, mouth_y, 5, 1)?;
+            fill(canvas, Color::BLACK, center.x - 3, mouth_y + 1, 1, 1)?;
+            fill(canvas, Color::BLACK, center.x + 3, mouth_y + 1, 1, 1)
+        }
+        Mood::Hover => {
+            //surprised open mouth
+            fill(canvas, Color::BLACK, center.x - 1, mouth_y - 1, 3, 3)
+        }
+        _ => {
+            //smile
+            fill(canvas, Color::BLACK, center.x - 2, mouth_y + 1, 5, 1)?;
+            fill(canvas, Color::BLACK, center.x - 3, mouth_y, 1, 1)?;
+            fill(canvas, Color::BLACK, center.x + 3, mouth_y, 1, 1)
+        }
+    }
+}
+
+///Draws seven segment digit, `segments` are the same as in `SEGMENTS`
+fn draw_digit(canvas: &mut AtlasCanvas, x: i32, segments: [bool; 7]) -> Result<(), String> {
+    fill(
+        canvas,
+        Color::BLACK,
+        x,
+        DIGITS_Y,
+        DIGIT_WIDTH as u32,
+        DIGIT_HEIGHT as u32,
+    )?;
+    let on = Color::RGB(255, 0, 0);
+    let off = Color::RGB(64, 0, 0);
+    let (left, right) = (x + 2, x + DIGIT_WIDTH - 5);
+    let (top, middle, bottom) = (
+        DIGITS_Y + 2,
+        DIGITS_Y + DIGIT_HEIGHT / 2 - 1,
+        DIGITS_Y + DIGIT_HEIGHT - 5,
+    );
+    let horizontal = (DIGIT_WIDTH - 8) as u32;
+    let vertical = (DIGIT_HEIGHT / 2 - 5) as u32;
+    //x, y, width and height of every segment in the same order as in `SEGMENTS`
+    let rects = [
+        (left + 3, top, horizontal, 3),
+        (right, top + 3, 3, vertical),
+        (right, middle + 3, 3, vertical),
+        (left + 3, bottom, horizontal, 3),
+        (left, middle + 3, 3, vertical),
+        (left, top + 3, 3, vertical),
+        (left + 3, middle, horizontal, 3),
+    ];
+    for (lit, (x, y, w, h)) in segments.iter().zip(rects.iter()) {
+        fill(canvas, if *lit { on } else { off }, *x, *y, *w, *h)?;
+    }
+    Ok(())
+}
+
+///Copies the row of square tiles under the digits and cuts the corners off,
+/// so that they become hexagons with a pointy top
+fn draw_hex_tiles(canvas: &mut AtlasCanvas) -> Result<(), String> {
+    let width = (TILE_TEXTURE_COUNT * TILE_SIZE) as u32;
+    let row = Rect::new(0, 0, width, TILE_SIZE as u32);
+    //surface can't be copied onto itself, so the row goes through a separate one
+    let mut tiles = Surface::new(width, TILE_SIZE as u32, PixelFormatEnum::RGBA8888)?;
+    canvas.surface().blit(row, &mut tiles, row)?;
+    tiles.set_blend_mode(BlendMode::None)?;
+    tiles.blit(
+        row,
+        canvas.surface_mut(),
+        Rect::new(0, HEX_Y, width, TILE_SIZE as u32),
+    )?;
+    let half = TILE_SIZE / 2;
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0));
+    for y in 0..TILE_SIZE {
+        //distance from the center is measured from the middle of the pixel
+        let dy = (2 * y + 1 - TILE_SIZE).abs();
+        for x in 0..TILE_SIZE {
+            let dx = (2 * x + 1 - TILE_SIZE).abs();
+            if 2 * dy > 4 * half - dx {
+                for i in 0..TILE_TEXTURE_COUNT {
+                    canvas.draw_point(Point::new(i * TILE_SIZE + x, HEX_Y + y))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+///Draws all of the textures into a single image and returns the image and where each texture is in it
+fn draw_atlas() -> Result<(Surface<'static>, BTreeMap<String, [i32; 4]>), String> {
+    let surface = Surface::new(ATLAS_WIDTH, ATLAS_HEIGHT, PixelFormatEnum::RGBA8888)?;
+    let mut canvas = surface.into_canvas()?;
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 0));
+    canvas.clear();
+    let mut rects: BTreeMap<String, [i32; 4]> = BTreeMap::new();
+    let tile_rect = |i: i32| [i * TILE_SIZE, 0, TILE_SIZE, TILE_SIZE];
+
+    draw_raised_tile(&mut canvas, 0, FACE_GREY)?;
+    rects.insert("tile_default".to_owned(), tile_rect(0));
+    draw_raised_tile(&mut canvas, TILE_SIZE, Color::RGB(224, 224, 224))?;
+    rects.insert("tile_selected".to_owned(), tile_rect(1));
+    draw_raised_tile(&mut canvas, TILE_SIZE * 2, FACE_GREY)?;
+    draw_tile_text(&mut canvas, TILE_SIZE * 2, "?", Color::BLACK)?;
+    rects.insert("tile_question".to_owned(), tile_rect(2));
+    draw_bomb(&mut canvas, TILE_SIZE * 3)?;
+    rects.insert("tile_bomb".to_owned(), tile_rect(3));
+    draw_flag(&mut canvas, TILE_SIZE * 4)?;
+    rects.insert("tile_flag".to_owned(), tile_rect(4));
+    for value in 0..=8 {
+        let i = 5 + value;
+        draw_flat_tile(&mut canvas, i * TILE_SIZE, FACE_GREY)?;
+        if value > 0 {
+            draw_tile_text(
+                &mut canvas,
+                i * TILE_SIZE,
+                &value.to_string(),
+                number_color(value),
+            )?;
+        }
+        rects.insert(format!("tile_{}", value), tile_rect(i));
+    }
+    for (i, state) in (14..).zip(multi_mine_tile_states()) {
+        let x = i * TILE_SIZE;
+        match state.strip_prefix("flag_") {
+            //number of flags is written in the top right corner
+            Some(flags) => {
+                draw_flag(&mut canvas, x)?;
+                for glyph in flags.chars() {
+                    draw_glyph(&mut canvas, x + TILE_SIZE - 5, 1, glyph, Color::BLACK, 1)?;
+                }
+            }
+            None => {
+                draw_flat_tile(&mut canvas, x, FACE_GREY)?;
+                let value = state.parse::<i32>().unwrap_or(0);
+                draw_tile_text(&mut canvas, x, &state, number_color(value))?;
+            }
+        }
+        rects.insert(format!("tile_{}", state), tile_rect(i));
+    }
+    draw_hex_tiles(&mut canvas)?;
+    //hex tiles are in the same order as square ones
+    let tile_names: Vec<String> = rects
+        .keys()
+        .filter(|name| name.starts_with("tile_"))
+        .cloned()
+        .collect();
+    for name in tile_names {
+        let mut rect = rects[&name];
+        rect[1] = HEX_Y;
+        rects.insert(name.replacen("tile_", "hex_", 1), rect);
+    }
+
+    let faces = [
+        ("face_default", Mood::Default),
+        ("face_loose", Mood::Loose),
+        ("face_win", Mood::Win),
+        ("face_hover", Mood::Hover),
+    ];
+    for (i, (name, mood)) in faces.iter().enumerate() {
+        let x = i as i32 * FACE_SIZE;
+        draw_face(&mut canvas, x, *mood)?;
+        rects.insert(name.to_string(), [x, FACES_Y, FACE_SIZE, FACE_SIZE]);
+    }
+    //player faces have the number of the player in the top left corner
+    let players = [('1', Color::RGB(0, 0, 255)), ('2', Color::RGB(255, 0, 0))];
+    for (i, (glyph, color)) in players.iter().enumerate() {
+        let x = (faces.len() + i) as i32 * FACE_SIZE;
+        draw_face(&mut canvas, x, Mood::Default)?;
+        draw_glyph(&mut canvas, x + 1, FACES_Y + 1, *glyph, *color, 1)?;
+        rects.insert(
+            format!("face_player_{}", i + 1),
+            [x, FACES_Y, FACE_SIZE, FACE_SIZE],
+        );
+    }
+
+    for (digit, segments) in SEGMENTS.iter().enumerate() {
+        let x = digit as i32 * DIGIT_WIDTH;
+        draw_digit(&mut canvas, x, *segments)?;
+        rects.insert(digit.to_string(), [x, DIGITS_Y, DIGIT_WIDTH, DIGIT_HEIGHT]);
+    }
+    let minus_x = 10 * DIGIT_WIDTH;
+    draw_digit(
+        &mut canvas,
+        minus_x,
+        [false, false, false, false, false, false, true],
+    )?;
+    rects.insert(
+        "minus".to_owned(),
+        [minus_x, DIGITS_Y, DIGIT_WIDTH, DIGIT_HEIGHT],
+    );
+
+    Ok((canvas.into_surface(), rects))
+}
+
+///Name the image with the fallback art is added to the atlas under, textures of the fallback theme are parts of it
+pub const FALLBACK_IMAGE: &str = "fallback";
+
+///Draws fallback art and adds it to the atlas as a single image.
+/// Image is kept in memory, so nothing is written to the disk
+pub fn generate_fallback_theme<T>(atlas: &mut TextureAtlas<T>) -> Result<Theme, String> {
+    let (surface, rects) = draw_atlas()?;
+    atlas.add_image(FALLBACK_IMAGE, &surface)?;
+    Ok(Theme {
+        name: "fallback".to_owned(),
+        textures: rects
+            .into_iter()
+            .map(|(name, rect)| {
+                (
+                    name,
+                    TextureSource {
+                        file: FALLBACK_IMAGE.to_owned(),
+                        rect,
+                    },
+                )
+            })
+            .collect(),
+        directory: PathBuf::new(),
+    })
+}
@@ -0,0 +1,1086 @@
+/**This file contains gameplay: the board and the state of the game are kept as resources
+ * and changed by the systems, event loop only turns input into the input queue
+ */
+use crate::clock::GameClock;
+use crate::controls::GameAction;
+use crate::events::{GameEvent, GameEvents};
+use crate::grid::{CellCoord, CellShape, Grid};
+use crate::minesweeper_ui::segmented_display_textures;
+use crate::rules::GameRules;
+use crate::save::BoardLayout;
+use crate::Field;
+use game_oxide_framework::components::Sprite;
+use game_oxide_framework::ui;
+use rand::rngs::StdRng;
+use specs::shrev::ReaderId;
+use specs::{Entity, Read, ReadExpect, System, World, WorldExt, Write, WriteStorage};
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub enum GameState {
+    ///Game is still happening
+    #[default]
+    Active,
+    ///Player is browsing menues
+    Setup,
+    ///Player has either won or lost
+    Ended,
+    ///Game is paused, timer is stopped and the field can not be interacted with
+    Paused,
+}
+
+///Field of the current game together with its counters
+#[derive(Clone, Debug, Default)]
+pub struct Board {
+    pub grid: Grid<Field>,
+    pub total_mine_count: u32,
+    ///Most mines a single tile can have, player can stack as many flags on a tile
+    pub mines_per_cell: u32,
+    ///Flags placed by player, question marks are not counted
+    pub flag_count: i32,
+    ///Player has revealed a mine, game is lost once win/loss system sees it
+    pub mine_hit: bool,
+    ///Some(true) if player won and Some(false) if player lost
+    pub result: Option<bool>,
+}
+
+impl Board {
+    pub fn new(grid: Grid<Field>, total_mine_count: u32, mines_per_cell: u32) -> Self {
+        Self {
+            grid,
+            total_mine_count,
+            mines_per_cell,
+            ..Default::default()
+        }
+    }
+
+    ///Number shown on the mine display, negative if player placed too many flags
+    pub fn mines_left(&self) -> i64 {
+        self.total_mine_count as i64 - self.flag_count as i64
+    }
+}
+
+///Action that player did on a cell of the field
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CellInput {
+    pub action: GameAction,
+    pub cell: CellCoord,
+}
+
+///Actions on the field made during the current frame, event loop fills it before running the systems
+#[derive(Clone, Debug, Default)]
+pub struct InputQueue {
+    pub inputs: Vec<CellInput>,
+}
+
+impl InputQueue {
+    fn contains(&self, actions: &[GameAction]) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| actions.contains(&input.action))
+    }
+}
+
+///Points player gets for every safe tile revealed on their turn
+pub const SAFE_TILE_POINTS: i64 = 1;
+///Points player loses for revealing a mine
+pub const MINE_PENALTY: i64 = 10;
+
+///Two players sharing the board and taking turns. Revealing a mine does not end the game,
+/// player loses points instead and the game goes on until every safe tile is revealed
+#[derive(Clone, Debug, Default)]
+pub struct HotSeat {
+    pub enabled: bool,
+    ///Index of the player whose turn it is
+    pub current: usize,
+    pub scores: [i64; 2],
+}
+
+impl HotSeat {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    ///Player with the most points, None if it's a draw
+    pub fn winner(&self) -> Option<usize> {
+        match self.scores[0].cmp(&self.scores[1]) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+///Name of the face texture that shows the player
+pub fn player_face(player: usize) -> String {
+    format!("face_player_{}", player + 1)
+}
+
+///Entities of the panel that show the state of the game
+#[derive(Clone, Debug)]
+pub struct PanelEntities {
+    pub face: Entity,
+    pub mine_display: Vec<Entity>,
+    pub time_display: Vec<Entity>,
+    ///Score displays of the players, empty unless players take turns
+    pub score_displays: Vec<Vec<Entity>>,
+}
+
+///Places bombs at given positions on an empty grid and calculates values for every tile.
+/// Tile with several mines is listed once for every mine
+fn grid_from_bombs(mut grid: Grid<Field>, bombs: &[CellCoord]) -> Grid<Field> {
+    for bomb in bombs {
+        grid[*bomb].bombs += 1;
+    }
+    //calculate values for bombs
+    for bomb in bombs {
+        //bomb with value is still a bomb, duh, so it counts itself too
+        grid[*bomb].value += 1;
+        //number on the tile counts mines among its neighbors, so the tiles that see the bomb are counted
+        for neighbor in grid.reverse_neighbors(*bomb) {
+            grid[neighbor].value += 1;
+            //mark this tile as border
+            if !grid[neighbor].has_bomb() {
+                grid[neighbor].border = true;
+            }
+        }
+    }
+    grid
+}
+
+///Places mines randomly on the empty grid
+pub fn generate_grid(
+    empty: Grid<Field>,
+    bomb_count: u32,
+    mines_per_cell: u32,
+    rng: &mut StdRng,
+) -> Grid<Field> {
+    let width = empty.width();
+    //generate all bombs
+    //every tile has a slot for each mine it can have and we pick random slots out of all of them,
+    //so no slot can be picked twice and no tile gets more mines than it can have
+    let slots_per_tile = mines_per_cell.max(1) as usize;
+    let bombs: Vec<CellCoord> = rand::seq::index::sample(
+        rng,
+        empty.cells().count() * slots_per_tile,
+        bomb_count as usize,
+    )
+    .into_iter()
+    .map(|slot| slot / slots_per_tile)
+    .map(|index| CellCoord::new(index % width, index / width))
+    .collect();
+    grid_from_bombs(empty, &bombs)
+}
+
+///Reveal the tile and all neighboring 0 tiles using flood algorithm,
+/// every tile that gets revealed is added to `revealed`.
+/// Tiles that still have to be visited are kept on a stack instead of recursion,
+/// so that empty areas of huge fields don't overflow the call stack
+pub fn reveal_block(cell: CellCoord, grid: &mut Grid<Field>, revealed: &mut Vec<CellCoord>) {
+    let mut stack: Vec<CellCoord> = vec![cell];
+    while let Some(cell) = stack.pop() {
+        //simple border check
+        if !grid.contains(cell) {
+            continue;
+        }
+        //don't reveal bombs
+        if grid[cell].has_bomb() || grid[cell].revealed {
+            continue;
+        }
+        //mark tile as visited, board renderer will draw it on the next frame
+        grid[cell].revealed = true;
+        revealed.push(cell);
+        //if this is a border we want to display the tile itself, but not go any further
+        if grid[cell].border {
+            continue;
+        }
+        //reveal neighbors
+        stack.extend(grid.flood_neighbors(cell));
+    }
+}
+
+///Attempts to flag a block, flags are stacked up to `max_flags` and then
+/// if question marks are enabled flagged block gets question mark next.
+/// If fails returns false
+pub fn flag_block(
+    cell: CellCoord,
+    grid: &mut Grid<Field>,
+    flag_count: &mut i32,
+    total_mine_count: i32,
+    max_flags: u32,
+    rules: &GameRules,
+) -> bool {
+    let field = match grid.get_mut(cell) {
+        Some(field) => field,
+        None => return false,
+    };
+    //if tile was revealed then we either know it's not a bomb or we lost the game
+    //no point in flagging it either way
+    if field.revealed {
+        return false;
+    }
+    let can_add_flag = !field.questioned
+        && field.flags < max_flags
+        && rules.can_place_flag(*flag_count, total_mine_count);
+    //nothing -> flag -> more flags(on multi-mine fields) -> question mark(if enabled) -> nothing
+    let (flags, questioned) = if field.questioned {
+        (0, false)
+    } else if can_add_flag {
+        (field.flags + 1, false)
+    } else if field.is_flagged() {
+        (0, rules.question_marks)
+    } else {
+        return false;
+    };
+    *flag_count += flags as i32 - field.flags as i32;
+    field.flags = flags;
+    field.questioned = questioned;
+    true
+}
+
+///If player is about to reveal a bomb with the very first click, bombs are moved
+/// to the first tiles that have room for them starting from the top left corner, like in the original game
+pub fn make_first_click_safe(cell: CellCoord, grid: &mut Grid<Field>, mines_per_cell: u32) {
+    let moved = match grid.get(cell) {
+        Some(field) if field.has_bomb() => field.bombs,
+        _ => return,
+    };
+    if grid.values().any(|field| field.revealed) {
+        return;
+    }
+    let mut bombs: Vec<CellCoord> = Vec::new();
+    for (position, field) in grid.iter() {
+        if position != cell {
+            bombs.extend(std::iter::repeat_n(position, field.bombs as usize));
+        }
+    }
+    let mut left = moved;
+    for (position, field) in grid.iter() {
+        if left == 0 {
+            break;
+        }
+        if position == cell || field.bombs >= mines_per_cell {
+            continue;
+        }
+        let added = (mines_per_cell - field.bombs).min(left);
+        bombs.extend(std::iter::repeat_n(position, added as usize));
+        left -= added;
+    }
+    //there is no room for the bombs anywhere else
+    if left > 0 {
+        return;
+    }
+    let mut new_grid = grid_from_bombs(grid.filled_like(Field::default()), &bombs);
+    //player could have placed flags before clicking
+    for (new_field, field) in new_grid.values_mut().zip(grid.values()) {
+        new_field.flags = field.flags;
+        new_field.questioned = field.questioned;
+        new_field.owner = field.owner;
+    }
+    *grid = new_grid;
+}
+
+///Reveals all neighbors of the revealed tile if player has placed as many flags around it
+/// as the number on the tile says. Returns every bomb among the neighbors that were revealed,
+/// with wrong flags there can be more than one
+pub fn chord_block(
+    cell: CellCoord,
+    grid: &mut Grid<Field>,
+    revealed: &mut Vec<CellCoord>,
+) -> Vec<CellCoord> {
+    let mut hit_bombs: Vec<CellCoord> = Vec::new();
+    if !grid.get(cell).map(|field| field.revealed).unwrap_or(false) {
+        return hit_bombs;
+    }
+    let neighbors = grid.neighbors(cell);
+    let flags: i32 = neighbors.iter().map(|n| grid[*n].flags as i32).sum();
+    if flags != grid[cell].value {
+        return hit_bombs;
+    }
+    for neighbor in neighbors {
+        let field = &grid[neighbor];
+        //mines that were already hit are revealed when players take turns
+        if field.is_flagged() || field.revealed {
+            continue;
+        }
+        if field.has_bomb() {
+            hit_bombs.push(neighbor);
+        } else {
+            reveal_block(neighbor, grid, revealed);
+        }
+    }
+    hit_bombs
+}
+
+///Checks if every tile has as many flags as mines,
+/// otherwise with over flagging enabled player could win by flagging everything
+pub fn check_mines(grid: &Grid<Field>) -> bool {
+    grid.values().all(|field| field.bombs == field.flags)
+}
+
+///Finds a safe tile that player has not revealed yet,
+/// tiles next to already revealed ones are preferred so that hint actually helps
+fn find_hint(grid: &Grid<Field>) -> Option<CellCoord> {
+    let mut fallback: Option<CellCoord> = None;
+    for (cell, field) in grid.iter() {
+        if field.has_bomb() || field.revealed || field.is_flagged() {
+            continue;
+        }
+        if grid.neighbors(cell).iter().any(|n| grid[*n].revealed) {
+            return Some(cell);
+        }
+        //empty tiles reveal the most, so they make the best hint if nothing is revealed yet
+        if fallback.is_none() || field.value == 0 && !field.border {
+            fallback = Some(cell);
+        }
+    }
+    fallback
+}
+
+///Collects positions of all tiles that match the condition, in the format used by save files
+pub fn collect_positions(grid: &Grid<Field>, condition: fn(&Field) -> bool) -> Vec<[usize; 2]> {
+    grid.iter()
+        .filter(|(_, field)| condition(field))
+        .map(|(cell, _)| cell.to_row_col())
+        .collect()
+}
+
+///Collects positions of the tiles in the format used by save files,
+/// every tile is repeated as many times as `count` says
+pub fn collect_counts(grid: &Grid<Field>, count: fn(&Field) -> u32) -> Vec<[usize; 2]> {
+    grid.iter()
+        .flat_map(|(cell, field)| std::iter::repeat_n(cell.to_row_col(), count(field) as usize))
+        .collect()
+}
+
+pub fn board_layout(board: &Board) -> BoardLayout {
+    let grid = &board.grid;
+    BoardLayout {
+        width: grid.width(),
+        height: grid.height(),
+        shape: grid.shape(),
+        topology: grid.topology(),
+        neighborhood: grid.neighborhood().cloned(),
+        mines_per_cell: board.mines_per_cell,
+        mines: collect_counts(grid, |field| field.bombs),
+    }
+}
+
+///Name of the rule set that decides which tiles are neighbors on the grid
+pub fn rule_set_name<T>(grid: &Grid<T>) -> &str {
+    match (grid.neighborhood(), grid.shape()) {
+        (Some(neighborhood), _) => neighborhood.name(),
+        (None, CellShape::Square) => "standard",
+        (None, CellShape::Hex) => "hex",
+    }
+}
+
+///Grid of the size and shape from the layout without any mines
+pub fn empty_grid(layout: &BoardLayout) -> Grid<Field> {
+    Grid::with_shape(layout.width, layout.height, layout.shape, Field::default())
+        .with_topology(layout.topology)
+        .with_neighborhood(layout.neighborhood.clone())
+}
+
+pub fn grid_from_layout(layout: &BoardLayout) -> Grid<Field> {
+    let bombs: Vec<CellCoord> = layout
+        .mines
+        .iter()
+        .map(|mine| CellCoord::from_row_col(*mine))
+        .collect();
+    grid_from_bombs(empty_grid(layout), &bombs)
+}
+
+///Places flags on the cells player flagged
+pub struct FlagSystem;
+
+impl<'a> System<'a> for FlagSystem {
+    type SystemData = (
+        Read<'a, InputQueue>,
+        Read<'a, GameRules>,
+        Read<'a, GameState>,
+        Write<'a, Board>,
+        Write<'a, GameEvents>,
+    );
+
+    fn run(&mut self, (queue, rules, state, mut board, mut events): Self::SystemData) {
+        if *state != GameState::Active {
+            return;
+        }
+        let board = &mut *board;
+        let total_mine_count = board.total_mine_count as i32;
+        for input in queue
+            .inputs
+            .iter()
+            .filter(|input| input.action == GameAction::Flag)
+        {
+            let old_flags = board.grid.get(input.cell).map(|field| field.flags);
+            if flag_block(
+                input.cell,
+                &mut board.grid,
+                &mut board.flag_count,
+                total_mine_count,
+                board.mines_per_cell,
+                &rules,
+            ) {
+                //question mark replacing the flag counts as removing it
+                let new_flags = board.grid[input.cell].flags;
+                match old_flags {
+                    Some(old) if new_flags > old => {
+                        events.single_write(GameEvent::Flagged(input.cell))
+                    }
+                    Some(old) if new_flags < old => {
+                        events.single_write(GameEvent::Unflagged(input.cell))
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+///Reveals cells, this includes chording and hints
+pub struct RevealSystem;
+
+impl<'a> System<'a> for RevealSystem {
+    type SystemData = (
+        Read<'a, InputQueue>,
+        Read<'a, GameRules>,
+        Read<'a, GameState>,
+        Write<'a, Board>,
+        Write<'a, GameEvents>,
+    );
+
+    fn run(&mut self, (queue, rules, state, mut board, mut events): Self::SystemData) {
+        if *state != GameState::Active {
+            return;
+        }
+        let mut revealed: Vec<CellCoord> = Vec::new();
+        for input in queue.inputs.iter() {
+            let mines: Vec<CellCoord> = match input.action {
+                //when players take turns hit mines stay revealed, clicking them again changes nothing
+                GameAction::Reveal
+                    if board
+                        .grid
+                        .get(input.cell)
+                        .map(|field| field.revealed)
+                        .unwrap_or(true) =>
+                {
+                    Vec::new()
+                }
+                GameAction::Reveal => {
+                    if rules.safe_first_click {
+                        let mines_per_cell = board.mines_per_cell;
+                        make_first_click_safe(input.cell, &mut board.grid, mines_per_cell);
+                    }
+                    reveal_block(input.cell, &mut board.grid, &mut revealed);
+                    board
+                        .grid
+                        .get(input.cell)
+                        .filter(|field| field.has_bomb())
+                        .map(|_| input.cell)
+                        .into_iter()
+                        .collect()
+                }
+                GameAction::Chord => chord_block(input.cell, &mut board.grid, &mut revealed),
+                GameAction::Hint => {
+                    if let Some(hint) = find_hint(&board.grid) {
+                        reveal_block(hint, &mut board.grid, &mut revealed);
+                    }
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            };
+            events.iter_write(revealed.drain(..).map(GameEvent::CellRevealed));
+            //when players take turns every mine costs points, so each of them is sent
+            if !mines.is_empty() {
+                board.mine_hit = true;
+                events.iter_write(mines.into_iter().map(GameEvent::MineHit));
+            }
+        }
+    }
+}
+
+///Scores the moves of the players and passes the turn when players take turns.
+/// Every move that reveals something ends the turn, flags are shared and don't end it
+pub struct HotSeatSystem {
+    reader: ReaderId<GameEvent>,
+}
+
+impl HotSeatSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: world.write_resource::<GameEvents>().register_reader(),
+        }
+    }
+}
+
+impl<'a> System<'a> for HotSeatSystem {
+    type SystemData = (
+        Read<'a, GameState>,
+        Write<'a, HotSeat>,
+        Write<'a, Board>,
+        Write<'a, GameEvents>,
+    );
+
+    fn run(&mut self, (state, mut hot_seat, mut board, mut events): Self::SystemData) {
+        //events are read even when they are not needed, so that they don't pile up for later
+        let new_events: Vec<GameEvent> = events.read(&mut self.reader).cloned().collect();
+        if !hot_seat.enabled || *state != GameState::Active {
+            return;
+        }
+        let mut points = 0;
+        let mut turn_over = false;
+        let mut mines: Vec<CellCoord> = Vec::new();
+        for event in new_events {
+            match event {
+                //mines that were revealed by this system are sent as revealed cells too
+                GameEvent::CellRevealed(cell) if !board.grid[cell].has_bomb() => {
+                    points += SAFE_TILE_POINTS;
+                    turn_over = true;
+                }
+                GameEvent::MineHit(cell) => {
+                    points -= MINE_PENALTY;
+                    turn_over = true;
+                    mines.push(cell);
+                }
+                _ => {}
+            }
+        }
+        //mine stays revealed so that nobody can click on it again
+        board.mine_hit = false;
+        for mine in mines.iter() {
+            board.grid[*mine].revealed = true;
+        }
+        events.iter_write(mines.into_iter().map(GameEvent::CellRevealed));
+        let player = hot_seat.current;
+        if points != 0 {
+            hot_seat.scores[player] += points;
+            events.single_write(GameEvent::ScoreChanged {
+                player,
+                score: hot_seat.scores[player],
+            });
+        }
+        if turn_over {
+            hot_seat.current = 1 - player;
+            events.single_write(GameEvent::TurnChanged(hot_seat.current));
+        }
+    }
+}
+
+///Ends the game once player hits a mine or flags all of them.
+/// When players take turns or race, game ends once every safe tile is revealed
+pub struct WinLossSystem;
+
+impl<'a> System<'a> for WinLossSystem {
+    type SystemData = (
+        Read<'a, InputQueue>,
+        Read<'a, HotSeat>,
+        Read<'a, GameRules>,
+        Write<'a, GameState>,
+        Write<'a, Board>,
+        Write<'a, GameEvents>,
+    );
+
+    fn run(
+        &mut self,
+        (queue, hot_seat, rules, mut state, mut board, mut events): Self::SystemData,
+    ) {
+        if *state != GameState::Active {
+            return;
+        }
+        let cleared = || {
+            board
+                .grid
+                .values()
+                .all(|field| field.revealed || field.has_bomb())
+        };
+        //win is only checked after flagging, like in the original game
+        let win = if hot_seat.enabled {
+            //scores decide who won, so the game itself is always won
+            if cleared() {
+                true
+            } else {
+                return;
+            }
+        } else if board.mine_hit {
+            false
+        } else if queue.contains(&[GameAction::Flag]) && check_mines(&board.grid)
+            || rules.win_on_clear && cleared()
+        {
+            true
+        } else {
+            return;
+        };
+        if !win {
+            //reveal all bombs
+            let bombs: Vec<CellCoord> = board
+                .grid
+                .iter()
+                .filter(|(_, field)| field.has_bomb() && !field.revealed)
+                .map(|(cell, _)| cell)
+                .collect();
+            for bomb in bombs.iter() {
+                board.grid[*bomb].revealed = true;
+            }
+            events.iter_write(bombs.into_iter().map(GameEvent::CellRevealed));
+        }
+        board.result = Some(win);
+        *state = GameState::Ended;
+        events.single_write(if win {
+            GameEvent::GameWon
+        } else {
+            GameEvent::GameLost
+        });
+    }
+}
+
+///Runs the game clock: it starts on the first reveal, stops while the game is paused
+/// and stops for good once the game has ended
+#[derive(Default)]
+pub struct TimerSystem {
+    ///Seconds that were sent with the last tick
+    last_tick: Option<u64>,
+}
+
+impl<'a> System<'a> for TimerSystem {
+    type SystemData = (
+        Read<'a, InputQueue>,
+        Read<'a, GameState>,
+        Write<'a, GameClock>,
+        Write<'a, GameEvents>,
+    );
+
+    fn run(&mut self, (queue, state, mut clock, mut events): Self::SystemData) {
+        match *state {
+            GameState::Active => {
+                clock.resume();
+                if queue.contains(&[GameAction::Reveal, GameAction::Chord, GameAction::Hint]) {
+                    clock.start();
+                }
+            }
+            GameState::Ended => clock.stop(),
+            GameState::Paused | GameState::Setup => {
+                //clock that was not started yet has nothing to pause
+                if clock.is_started() {
+                    clock.pause();
+                }
+            }
+        }
+        let seconds = clock.elapsed().as_secs();
+        if self.last_tick != Some(seconds) {
+            self.last_tick = Some(seconds);
+            events.single_write(GameEvent::TimerTick(seconds));
+        }
+    }
+}
+
+///Shows the state of the game on the panel: mines left, time and the face.
+/// When players take turns it shows their scores and the face shows whose turn it is.
+/// Panel is only changed when events say that something it shows has changed
+pub struct DisplaySyncSystem {
+    reader: ReaderId<GameEvent>,
+}
+
+impl DisplaySyncSystem {
+    pub fn new(world: &mut World) -> Self {
+        Self {
+            reader: world.write_resource::<GameEvents>().register_reader(),
+        }
+    }
+}
+
+impl<'a> System<'a> for DisplaySyncSystem {
+    type SystemData = (
+        ReadExpect<'a, PanelEntities>,
+        Read<'a, Board>,
+        Read<'a, HotSeat>,
+        Read<'a, GameEvents>,
+        WriteStorage<'a, Sprite>,
+        WriteStorage<'a, ui::Button>,
+    );
+
+    fn run(
+        &mut self,
+        (panel, board, hot_seat, events, mut sprites, mut buttons): Self::SystemData,
+    ) {
+        let mut set_display = |display: &Vec<Entity>, value: i64| {
+            let values = segmented_display_textures(display.len(), value);
+            for (entity, value) in display.iter().zip(values) {
+                if let Some(sprite) = sprites.get_mut(*entity) {
+                    sprite.name = value;
+                }
+            }
+        };
+        let mut face: Option<String> = None;
+        for event in events.read(&mut self.reader) {
+            match event {
+                GameEvent::Flagged(_) | GameEvent::Unflagged(_) => {
+                    set_display(&panel.mine_display, board.mines_left())
+                }
+                GameEvent::TimerTick(seconds) => set_display(&panel.time_display, *seconds as i64),
+                GameEvent::GameStarted => {
+                    set_display(&panel.mine_display, board.mines_left());
+                    for (display, score) in panel.score_displays.iter().zip(hot_seat.scores) {
+                        set_display(display, score);
+                    }
+                    face = Some(if hot_seat.enabled {
+                        player_face(hot_seat.current)
+                    } else {
+                        "face_default".to_owned()
+                    });
+                }
+                GameEvent::TurnChanged(player) => face = Some(player_face(*player)),
+                GameEvent::ScoreChanged { player, score } => {
+                    if let Some(display) = panel.score_displays.get(*player) {
+                        set_display(display, *score);
+                    }
+                }
+                //when players take turns the face of the winner is shown
+                GameEvent::GameWon => {
+                    face = Some(match hot_seat.winner().filter(|_| hot_seat.enabled) {
+                        Some(winner) => player_face(winner),
+                        None => "face_win".to_owned(),
+                    })
+                }
+                GameEvent::GameLost => face = Some("face_loose".to_owned()),
+                _ => {}
+            }
+        }
+        if let Some(face) = face {
+            if let Some(button) = buttons.get_mut(panel.face) {
+                button.normal_texture_name = Some(face);
+            }
+        }
+    }
+}
+
+///Makes the board the current game: resets the clock and the state of the game
+/// and lets everything that listens to the events know that new game has started
+pub fn start_game(world: &mut World, board: Board) {
+    world.insert(board);
+    //scores start from zero and first player goes first again
+    {
+        let mut hot_seat = world.entry::<HotSeat>().or_insert_with(HotSeat::default);
+        *hot_seat = HotSeat::new(hot_seat.enabled);
+    }
+    world.write_resource::<GameClock>().reset();
+    *world.write_resource::<GameState>() = GameState::Active;
+    world
+        .write_resource::<GameEvents>()
+        .single_write(GameEvent::GameStarted);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::MAX_FIELD_SIDE;
+    use specs::RunNow;
+
+    #[test]
+    fn revealed_mine_is_only_hit_once() {
+        let mut world = World::new();
+        let grid = grid_from_bombs(Grid::new(3, 3, Field::default()), &[CellCoord::new(1, 1)]);
+        world.insert(Board::new(grid, 1, 1));
+        world.insert(GameRules {
+            safe_first_click: false,
+            ..GameRules::default()
+        });
+        world.insert(GameState::Active);
+        let mut events = GameEvents::new();
+        let mut reader = events.register_reader();
+        world.insert(events);
+        let mine = CellCoord::new(1, 1);
+        world.insert(InputQueue {
+            inputs: vec![CellInput {
+                action: GameAction::Reveal,
+                cell: mine,
+            }],
+        });
+        for _ in 0..2 {
+            RevealSystem.run_now(&world);
+            //hot-seat system reveals the hit mine and lets the game go on
+            let mut board = world.write_resource::<Board>();
+            board.grid[mine].revealed = true;
+            board.mine_hit = false;
+        }
+        let hits = world
+            .read_resource::<GameEvents>()
+            .read(&mut reader)
+            .filter(|event| matches!(event, GameEvent::MineHit(_)))
+            .count();
+        assert_eq!(hits, 1);
+    }
+
+    #[test]
+    fn chord_over_two_mines_costs_points_for_both() {
+        let mut world = World::new();
+        let mines = [CellCoord::new(0, 0), CellCoord::new(2, 0)];
+        let mut grid = grid_from_bombs(Grid::new(3, 2, Field::default()), &mines);
+        let center = CellCoord::new(1, 1);
+        grid[center].revealed = true;
+        //two flags next to the 2, but neither of them is on a mine
+        grid[CellCoord::new(0, 1)].flags = 1;
+        grid[CellCoord::new(2, 1)].flags = 1;
+        world.insert(Board::new(grid, 2, 1));
+        world.insert(GameRules::default());
+        world.insert(HotSeat::new(true));
+        world.insert(GameState::Active);
+        let mut events = GameEvents::new();
+        let mut reader = events.register_reader();
+        world.insert(events);
+        let mut hot_seat = HotSeatSystem::new(&mut world);
+        world.insert(InputQueue {
+            inputs: vec![CellInput {
+                action: GameAction::Chord,
+                cell: center,
+            }],
+        });
+        RevealSystem.run_now(&world);
+        hot_seat.run_now(&world);
+
+        let hits: Vec<CellCoord> = world
+            .read_resource::<GameEvents>()
+            .read(&mut reader)
+            .filter_map(|event| match event {
+                GameEvent::MineHit(cell) => Some(*cell),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(hits, mines);
+        let players = world.read_resource::<HotSeat>();
+        assert_eq!(players.scores, [SAFE_TILE_POINTS - 2 * MINE_PENALTY, 0]);
+        assert_eq!(players.current, 1);
+        let board = world.read_resource::<Board>();
+        assert!(mines.iter().all(|mine| board.grid[*mine].revealed));
+        assert!(!board.mine_hit);
+    }
+
+    #[test]
+    fn flood_reveal_opens_the_largest_empty_field() {
+        let mut grid = Grid::new(MAX_FIELD_SIDE, MAX_FIELD_SIDE, Field::default());
+        let mut revealed: Vec<CellCoord> = Vec::new();
+        reveal_block(CellCoord::new(0, 0), &mut grid, &mut revealed);
+        assert_eq!(revealed.len(), MAX_FIELD_SIDE * MAX_FIELD_SIDE);
+        assert!(grid.values().all(|field| field.revealed));
+    }
+
+    #[test]
+    fn flood_reveal_stops_at_the_border_tiles() {
+        let empty = Grid::new(5, 1, Field::default());
+        let mut grid = grid_from_bombs(empty, &[CellCoord::new(4, 0)]);
+        let mut revealed: Vec<CellCoord> = Vec::new();
+        reveal_block(CellCoord::new(0, 0), &mut grid, &mut revealed);
+        assert_eq!(revealed.len(), 4);
+        assert!(!grid[CellCoord::new(4, 0)].revealed);
+    }
+
+    #[test]
+    fn timer_starts_on_the_first_reveal() {
+        let mut world = World::new();
+        world.insert(GameState::Active);
+        world.insert(GameClock::default());
+        world.insert(GameEvents::new());
+        let mut timer = TimerSystem::default();
+        for action in [GameAction::Flag, GameAction::MoveUp] {
+            world.insert(InputQueue {
+                inputs: vec![CellInput {
+                    action,
+                    cell: CellCoord::new(0, 0),
+                }],
+            });
+            timer.run_now(&world);
+            assert!(!world.read_resource::<GameClock>().is_started());
+        }
+        world.insert(InputQueue {
+            inputs: vec![CellInput {
+                action: GameAction::Reveal,
+                cell: CellCoord::new(0, 0),
+            }],
+        });
+        timer.run_now(&world);
+        assert!(world.read_resource::<GameClock>().is_started());
+        *world.write_resource::<GameState>() = GameState::Ended;
+        timer.run_now(&world);
+        assert!(world.read_resource::<GameClock>().is_stopped());
+    }
+
+    fn rules(allow_over_flagging: bool, question_marks: bool) -> GameRules {
+        GameRules {
+            allow_over_flagging,
+            question_marks,
+            safe_first_click: false,
+            win_on_clear: false,
+        }
+    }
+
+    ///Flags the tile and returns the state it ended up in
+    fn flag(
+        grid: &mut Grid<Field>,
+        cell: CellCoord,
+        flag_count: &mut i32,
+        rules: &GameRules,
+    ) -> (bool, u32, bool) {
+        let changed = flag_block(cell, grid, flag_count, 1, 1, rules);
+        (changed, grid[cell].flags, grid[cell].questioned)
+    }
+
+    #[test]
+    fn flag_past_the_mine_count_is_refused_unless_allowed() {
+        let (first, second) = (CellCoord::new(0, 0), CellCoord::new(1, 0));
+        let mut grid = Grid::new(3, 1, Field::default());
+        let mut flag_count = 0;
+        let strict = rules(false, false);
+        assert_eq!(
+            flag(&mut grid, first, &mut flag_count, &strict),
+            (true, 1, false)
+        );
+        assert_eq!(
+            flag(&mut grid, second, &mut flag_count, &strict),
+            (false, 0, false)
+        );
+        assert_eq!(flag_count, 1);
+
+        let original = rules(true, false);
+        assert_eq!(
+            flag(&mut grid, second, &mut flag_count, &original),
+            (true, 1, false)
+        );
+        assert_eq!(flag_count, 2);
+        //taking a flag off is always allowed, even when there are too many
+        assert_eq!(
+            flag(&mut grid, first, &mut flag_count, &strict),
+            (true, 0, false)
+        );
+        assert_eq!(flag_count, 1);
+    }
+
+    #[test]
+    fn flag_goes_through_question_mark_only_when_enabled() {
+        let cell = CellCoord::new(0, 0);
+        let mut grid = Grid::new(2, 1, Field::default());
+        let mut flag_count = 0;
+        let questions = rules(false, true);
+        assert_eq!(
+            flag(&mut grid, cell, &mut flag_count, &questions),
+            (true, 1, false)
+        );
+        assert_eq!(
+            flag(&mut grid, cell, &mut flag_count, &questions),
+            (true, 0, true)
+        );
+        assert_eq!(flag_count, 0);
+        assert_eq!(
+            flag(&mut grid, cell, &mut flag_count, &questions),
+            (true, 0, false)
+        );
+
+        let no_questions = rules(false, false);
+        assert_eq!(
+            flag(&mut grid, cell, &mut flag_count, &no_questions),
+            (true, 1, false)
+        );
+        assert_eq!(
+            flag(&mut grid, cell, &mut flag_count, &no_questions),
+            (true, 0, false)
+        );
+        assert_eq!(flag_count, 0);
+
+        //revealed tiles can't be flagged at all
+        grid[cell].revealed = true;
+        assert_eq!(
+            flag(&mut grid, cell, &mut flag_count, &questions),
+            (false, 0, false)
+        );
+    }
+
+    #[test]
+    fn multi_mine_tile_takes_a_flag_for_every_mine() {
+        let cell = CellCoord::new(0, 0);
+        let mut grid = Grid::new(2, 1, Field::default());
+        let mut flag_count = 0;
+        let questions = rules(false, true);
+        for flags in 1..=3 {
+            assert!(flag_block(
+                cell,
+                &mut grid,
+                &mut flag_count,
+                10,
+                3,
+                &questions
+            ));
+            assert_eq!(grid[cell].flags, flags);
+        }
+        assert_eq!(flag_count, 3);
+        assert!(flag_block(
+            cell,
+            &mut grid,
+            &mut flag_count,
+            10,
+            3,
+            &questions
+        ));
+        assert_eq!((grid[cell].flags, grid[cell].questioned), (0, true));
+        assert_eq!(flag_count, 0);
+    }
+
+    #[test]
+    fn mines_are_found_when_every_tile_has_as_many_flags_as_mines() {
+        let mut grid = Grid::new(3, 1, Field::default());
+        grid[CellCoord::new(0, 0)].bombs = 2;
+        grid[CellCoord::new(1, 0)].bombs = 1;
+        assert!(!check_mines(&grid));
+        grid[CellCoord::new(0, 0)].flags = 1;
+        grid[CellCoord::new(1, 0)].flags = 1;
+        //same number of flags in total, but they are on the wrong tiles
+        grid[CellCoord::new(2, 0)].flags = 1;
+        assert!(!check_mines(&grid));
+        grid[CellCoord::new(2, 0)].flags = 0;
+        assert!(!check_mines(&grid));
+        grid[CellCoord::new(0, 0)].flags = 2;
+        assert!(check_mines(&grid));
+        //extra flag on a safe tile is a mistake too
+        grid[CellCoord::new(2, 0)].flags = 1;
+        assert!(!check_mines(&grid));
+    }
+
+    #[test]
+    fn race_is_won_once_every_safe_tile_is_revealed() {
+        for win_on_clear in [false, true] {
+            let mut world = World::new();
+            let grid = grid_from_bombs(Grid::new(2, 1, Field::default()), &[CellCoord::new(1, 0)]);
+            world.insert(Board::new(grid, 1, 1));
+            world.insert(GameRules {
+                safe_first_click: false,
+                win_on_clear,
+                ..GameRules::default()
+            });
+            world.insert(HotSeat::new(false));
+            world.insert(GameState::Active);
+            world.insert(GameEvents::new());
+            world.insert(InputQueue {
+                inputs: vec![CellInput {
+                    action: GameAction::Reveal,
+                    cell: CellCoord::new(0, 0),
+                }],
+            });
+            RevealSystem.run_now(&world);
+            WinLossSystem.run_now(&world);
+            //without the race rule the last mine still has to be flagged
+            let expected = if win_on_clear {
+                (GameState::Ended, Some(true))
+            } else {
+                (GameState::Active, None)
+            };
+            assert_eq!(
+                (
+                    *world.read_resource::<GameState>(),
+                    world.read_resource::<Board>().result
+                ),
+                expected
+            );
+        }
+    }
+}
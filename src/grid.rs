@@ -0,0 +1,452 @@
+/**This file contains the grid that stores the field and coordinates of its cells.
+ * Cells are always addressed by column and row, so there is no way to mix up x and y.
+ * Hex cells use offset coordinates: every odd row is shifted right by half of a cell.
+ * Which cells are neighbors is decided by the shape of the cells and the topology of the grid together,
+ * shape gives offsets to the neighbors and topology decides what happens when offset goes past the edge.
+ * Grid can also have a neighborhood that gives the offsets instead of the shape
+ */
+use crate::neighborhood::Neighborhood;
+use serde::{Deserialize, Serialize};
+use std::ops::{Index, IndexMut};
+
+///Shape of the cells, it decides which cells are next to each other
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CellShape {
+    ///Every cell has 8 neighbors
+    #[default]
+    Square,
+    ///Every cell has 6 neighbors
+    Hex,
+}
+
+///What happens at the edges of the grid
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Topology {
+    ///Cells on the edges have fewer neighbors
+    #[default]
+    Bounded,
+    ///Edges wrap around, so the leftmost column is next to the rightmost one
+    /// and the top row is next to the bottom one
+    Wrapped,
+}
+
+///Column and row offsets of the neighbors of a hex cell in an even row
+const HEX_EVEN_ROW_NEIGHBORS: [(i32, i32); 6] =
+    [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+///Column and row offsets of the neighbors of a hex cell in an odd row
+const HEX_ODD_ROW_NEIGHBORS: [(i32, i32); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+///Column and row offsets of the neighbors of a square cell that share a side with it
+const SQUARE_SIDE_NEIGHBORS: [(i32, i32); 4] = [(0, -1), (-1, 0), (0, 1), (1, 0)];
+
+///Position of a cell in the grid, column is the horizontal one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CellCoord {
+    pub col: usize,
+    pub row: usize,
+}
+
+impl CellCoord {
+    pub fn new(col: usize, row: usize) -> Self {
+        Self { col, row }
+    }
+
+    ///Cell that is `cols` columns and `rows` rows away from this one,
+    /// None if it would be left of or above the grid
+    pub fn offset(&self, cols: i32, rows: i32) -> Option<CellCoord> {
+        let col = self.col as i64 + cols as i64;
+        let row = self.row as i64 + rows as i64;
+        if col < 0 || row < 0 {
+            return None;
+        }
+        Some(CellCoord::new(col as usize, row as usize))
+    }
+
+    ///Files store cells as [row, column] pairs
+    pub fn from_row_col(position: [usize; 2]) -> Self {
+        CellCoord::new(position[1], position[0])
+    }
+
+    pub fn to_row_col(&self) -> [usize; 2] {
+        [self.row, self.col]
+    }
+}
+
+///Rectangular grid of cells stored row by row
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    shape: CellShape,
+    topology: Topology,
+    neighborhood: Option<Neighborhood>,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    ///Creates grid of square cells where every cell has the given value
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Grid::with_shape(width, height, CellShape::Square, value)
+    }
+
+    ///Creates grid of cells of the given shape where every cell has the given value
+    pub fn with_shape(width: usize, height: usize, shape: CellShape, value: T) -> Self {
+        Self {
+            width,
+            height,
+            shape,
+            topology: Topology::Bounded,
+            neighborhood: None,
+            cells: vec![value; width * height],
+        }
+    }
+
+    ///Same grid, but with the given topology
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    ///Same grid, but neighbors are decided by the neighborhood instead of the shape of the cells
+    pub fn with_neighborhood(mut self, neighborhood: Option<Neighborhood>) -> Self {
+        self.neighborhood = neighborhood;
+        self
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn shape(&self) -> CellShape {
+        self.shape
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn neighborhood(&self) -> Option<&Neighborhood> {
+        self.neighborhood.as_ref()
+    }
+
+    ///Grid of the same size with the same neighbors, where every cell has the given value
+    pub fn filled_like<U: Clone>(&self, value: U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            shape: self.shape,
+            topology: self.topology,
+            neighborhood: self.neighborhood.clone(),
+            cells: vec![value; self.cells.len()],
+        }
+    }
+
+    pub fn contains(&self, cell: CellCoord) -> bool {
+        cell.col < self.width && cell.row < self.height
+    }
+
+    pub fn get(&self, cell: CellCoord) -> Option<&T> {
+        if self.contains(cell) {
+            Some(&self.cells[cell.row * self.width + cell.col])
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, cell: CellCoord) -> Option<&mut T> {
+        if self.contains(cell) {
+            Some(&mut self.cells[cell.row * self.width + cell.col])
+        } else {
+            None
+        }
+    }
+
+    ///Cell with the given index in the order cells are stored, row by row
+    pub fn cell_at_index(&self, index: usize) -> CellCoord {
+        CellCoord::new(index % self.width, index / self.width)
+    }
+
+    ///All cells of the grid, row by row
+    pub fn cells(&self) -> impl Iterator<Item = CellCoord> + '_ {
+        (0..self.cells.len()).map(move |index| self.cell_at_index(index))
+    }
+
+    ///Values of all cells together with their coordinates, row by row
+    pub fn iter(&self) -> impl Iterator<Item = (CellCoord, &T)> {
+        let width = self.width;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(index, value)| (CellCoord::new(index % width, index / width), value))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.cells.iter_mut()
+    }
+
+    ///Cell that is `cols` columns and `rows` rows away from the given one, if topology allows to go there
+    fn cell_at_offset(&self, cell: CellCoord, cols: i32, rows: i32) -> Option<CellCoord> {
+        match self.topology {
+            Topology::Bounded => cell
+                .offset(cols, rows)
+                .filter(|neighbor| self.contains(*neighbor)),
+            Topology::Wrapped => {
+                let wrap = |value: usize, offset: i32, size: usize| {
+                    (value as i64 + offset as i64).rem_euclid(size as i64) as usize
+                };
+                Some(CellCoord::new(
+                    wrap(cell.col, cols, self.width),
+                    wrap(cell.row, rows, self.height),
+                ))
+            }
+        }
+    }
+
+    ///Cells that are at the given offsets from the cell. On small wrapped grids several offsets
+    /// can lead to the same cell or back to the cell itself, such cells are only counted once
+    fn cells_at_offsets(&self, cell: CellCoord, offsets: &[(i32, i32)]) -> Vec<CellCoord> {
+        let mut cells: Vec<CellCoord> = Vec::new();
+        for (cols, rows) in offsets {
+            if let Some(neighbor) = self.cell_at_offset(cell, *cols, *rows) {
+                if neighbor != cell && !cells.contains(&neighbor) {
+                    cells.push(neighbor);
+                }
+            }
+        }
+        cells
+    }
+
+    ///Cells around the given one(up to 8 square or 6 hex ones, unless neighborhood says otherwise)
+    pub fn neighbors(&self, cell: CellCoord) -> Vec<CellCoord> {
+        if let Some(neighborhood) = &self.neighborhood {
+            return self.cells_at_offsets(cell, neighborhood.offsets());
+        }
+        match self.shape {
+            CellShape::Square => {
+                let mut offsets: Vec<(i32, i32)> = Vec::new();
+                for rows in -1..=1 {
+                    for cols in -1..=1 {
+                        if rows != 0 || cols != 0 {
+                            offsets.push((cols, rows));
+                        }
+                    }
+                }
+                self.cells_at_offsets(cell, &offsets)
+            }
+            CellShape::Hex if cell.row % 2 == 1 => {
+                self.cells_at_offsets(cell, &HEX_ODD_ROW_NEIGHBORS)
+            }
+            CellShape::Hex => self.cells_at_offsets(cell, &HEX_EVEN_ROW_NEIGHBORS),
+        }
+    }
+
+    ///Cells that have the given one as their neighbor. Neighborhood does not have to be symmetric,
+    /// for example it can only have the tile to the right, then this is the tile to the left
+    pub fn reverse_neighbors(&self, cell: CellCoord) -> Vec<CellCoord> {
+        match &self.neighborhood {
+            Some(neighborhood) => {
+                let reversed: Vec<(i32, i32)> = neighborhood
+                    .offsets()
+                    .iter()
+                    .map(|(cols, rows)| (-cols, -rows))
+                    .collect();
+                self.cells_at_offsets(cell, &reversed)
+            }
+            None => self.neighbors(cell),
+        }
+    }
+
+    ///Cells that flood reveal goes to from the given one. Square cells only share a side with 4 of
+    /// their neighbors, so flood does not go diagonally, hex cells share a side with all of them
+    pub fn flood_neighbors(&self, cell: CellCoord) -> Vec<CellCoord> {
+        if let Some(neighborhood) = &self.neighborhood {
+            return self.cells_at_offsets(cell, neighborhood.flood_offsets());
+        }
+        match self.shape {
+            CellShape::Square => self.cells_at_offsets(cell, &SQUARE_SIDE_NEIGHBORS),
+            CellShape::Hex => self.neighbors(cell),
+        }
+    }
+}
+
+impl<T> Index<CellCoord> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, cell: CellCoord) -> &T {
+        self.get(cell)
+            .unwrap_or_else(|| panic!("Cell {:?} is outside of the grid", cell))
+    }
+}
+
+impl<T> IndexMut<CellCoord> for Grid<T> {
+    fn index_mut(&mut self, cell: CellCoord) -> &mut T {
+        self.get_mut(cell)
+            .unwrap_or_else(|| panic!("Cell {:?} is outside of the grid", cell))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Cells sorted row by row, so that lists can be compared no matter the order of the offsets
+    fn sorted(mut cells: Vec<CellCoord>) -> Vec<CellCoord> {
+        cells.sort_by_key(|cell| (cell.row, cell.col));
+        cells
+    }
+
+    fn cells(positions: &[(usize, usize)]) -> Vec<CellCoord> {
+        sorted(
+            positions
+                .iter()
+                .map(|(col, row)| CellCoord::new(*col, *row))
+                .collect(),
+        )
+    }
+
+    fn hex_grid(width: usize, height: usize) -> Grid<()> {
+        Grid::with_shape(width, height, CellShape::Hex, ())
+    }
+
+    #[test]
+    fn hex_cell_in_an_even_row_leans_left() {
+        let grid = hex_grid(5, 5);
+        assert_eq!(
+            sorted(grid.neighbors(CellCoord::new(2, 2))),
+            cells(&[(1, 1), (2, 1), (1, 2), (3, 2), (1, 3), (2, 3)])
+        );
+    }
+
+    #[test]
+    fn hex_cell_in_an_odd_row_leans_right() {
+        let grid = hex_grid(5, 5);
+        assert_eq!(
+            sorted(grid.neighbors(CellCoord::new(2, 3))),
+            cells(&[(2, 2), (3, 2), (1, 3), (3, 3), (2, 4), (3, 4)])
+        );
+    }
+
+    #[test]
+    fn hex_cells_on_the_edges_lose_the_neighbors_outside() {
+        let grid = hex_grid(5, 5);
+        let neighbors = |col: usize, row: usize| sorted(grid.neighbors(CellCoord::new(col, row)));
+        //corners
+        assert_eq!(neighbors(0, 0), cells(&[(1, 0), (0, 1)]));
+        assert_eq!(neighbors(4, 0), cells(&[(3, 0), (3, 1), (4, 1)]));
+        assert_eq!(neighbors(0, 4), cells(&[(0, 3), (1, 4)]));
+        assert_eq!(neighbors(4, 4), cells(&[(3, 3), (4, 3), (3, 4)]));
+        //odd rows are shifted right, so they lose different neighbors on the sides than even ones
+        assert_eq!(
+            neighbors(0, 1),
+            cells(&[(0, 0), (1, 0), (1, 1), (0, 2), (1, 2)])
+        );
+        assert_eq!(neighbors(4, 1), cells(&[(4, 0), (3, 1), (4, 2)]));
+        assert_eq!(neighbors(0, 2), cells(&[(0, 1), (1, 2), (0, 3)]));
+        //top and bottom
+        assert_eq!(neighbors(2, 0), cells(&[(1, 0), (3, 0), (1, 1), (2, 1)]));
+    }
+
+    #[test]
+    fn hex_neighbors_see_each_other() {
+        let grid = hex_grid(5, 4);
+        for cell in grid.cells() {
+            for neighbor in grid.neighbors(cell) {
+                assert!(
+                    grid.neighbors(neighbor).contains(&cell),
+                    "{:?} is a neighbor of {:?}, but not the other way around",
+                    neighbor,
+                    cell
+                );
+            }
+        }
+    }
+
+    fn wrapped_grid(width: usize, height: usize, shape: CellShape) -> Grid<()> {
+        Grid::with_shape(width, height, shape, ()).with_topology(Topology::Wrapped)
+    }
+
+    #[test]
+    fn wrapped_corner_has_neighbors_on_the_opposite_edges() {
+        let grid = wrapped_grid(5, 4, CellShape::Square);
+        let corner = CellCoord::new(0, 0);
+        let expected = cells(&[
+            (4, 3),
+            (0, 3),
+            (1, 3),
+            (4, 0),
+            (1, 0),
+            (4, 1),
+            (0, 1),
+            (1, 1),
+        ]);
+        assert_eq!(sorted(grid.neighbors(corner)), expected);
+        assert_eq!(sorted(grid.reverse_neighbors(corner)), expected);
+        assert_eq!(
+            sorted(grid.flood_neighbors(corner)),
+            cells(&[(0, 3), (4, 0), (1, 0), (0, 1)])
+        );
+    }
+
+    #[test]
+    fn tiny_wrapped_grids_have_no_duplicate_neighbors() {
+        for (width, height) in [(1, 5), (5, 1), (2, 2), (1, 1)] {
+            let grid = wrapped_grid(width, height, CellShape::Square);
+            for cell in grid.cells() {
+                for neighbors in [
+                    grid.neighbors(cell),
+                    grid.reverse_neighbors(cell),
+                    grid.flood_neighbors(cell),
+                ] {
+                    assert!(!neighbors.contains(&cell), "{:?} is its own neighbor", cell);
+                    let mut unique = sorted(neighbors.clone());
+                    unique.dedup();
+                    assert_eq!(unique.len(), neighbors.len(), "{:?}", neighbors);
+                }
+            }
+        }
+        let grid = wrapped_grid(1, 5, CellShape::Square);
+        assert_eq!(
+            sorted(grid.neighbors(CellCoord::new(0, 0))),
+            cells(&[(0, 1), (0, 4)])
+        );
+        let grid = wrapped_grid(2, 2, CellShape::Square);
+        assert_eq!(
+            sorted(grid.neighbors(CellCoord::new(0, 0))),
+            cells(&[(1, 0), (0, 1), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn wrapped_hex_rows_keep_their_shift_across_the_seam() {
+        //with an even number of rows the last row is odd, so the top row fits under it
+        let grid = wrapped_grid(5, 4, CellShape::Hex);
+        let top = CellCoord::new(2, 0);
+        let expected = cells(&[(1, 3), (2, 3), (1, 0), (3, 0), (1, 1), (2, 1)]);
+        assert_eq!(sorted(grid.neighbors(top)), expected);
+        assert_eq!(sorted(grid.reverse_neighbors(top)), expected);
+        assert_eq!(sorted(grid.flood_neighbors(top)), expected);
+        let bottom = CellCoord::new(2, 3);
+        assert_eq!(
+            sorted(grid.neighbors(bottom)),
+            cells(&[(2, 2), (3, 2), (1, 3), (3, 3), (2, 0), (3, 0)])
+        );
+        for cell in grid.cells() {
+            let neighbors = grid.neighbors(cell);
+            assert_eq!(neighbors.len(), 6, "{:?}", cell);
+            for neighbor in neighbors {
+                assert!(grid.neighbors(neighbor).contains(&cell));
+            }
+        }
+    }
+}
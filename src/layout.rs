@@ -0,0 +1,341 @@
+/**This file contains layout of the window: how big the tiles are and where the field is.
+ * Layout is calculated again every time the window changes size.
+ * Hex tiles are drawn in squares too, but rows overlap by a quarter of a tile
+ * and every odd row is shifted right by half of a tile, so that hexagons fit together
+ */
+use crate::grid::{CellCoord, CellShape};
+use crate::options::MIN_TILE_SIZE;
+use nalgebra::Vector2;
+
+///Height of the panel with the face and the displays
+pub const CONTROLS_PANEL_SIZE: i32 = 100;
+
+///Width and height of the field in pixels
+pub fn field_pixels(shape: CellShape, width: usize, height: usize, tile_size: i32) -> Vector2<i32> {
+    match shape {
+        CellShape::Square => Vector2::new(width as i32 * tile_size, height as i32 * tile_size),
+        CellShape::Hex => Vector2::new(
+            width as i32 * tile_size + tile_size / 2,
+            (height as i32 - 1).max(0) * (tile_size * 3 / 4) + tile_size,
+        ),
+    }
+}
+
+///Checks if the point is inside of a hexagon with a pointy top that fits into a square
+/// with the given half size, the point is relative to the center of the hexagon
+fn is_inside_hex(point: Vector2<i32>, half_size: i32) -> bool {
+    let (x, y) = (point.x.abs(), point.y.abs());
+    //sides are vertical and the top and bottom halves of the hexagon are triangles
+    //that go from the middle of the side to the top
+    x <= half_size && 2 * y <= 2 * half_size - x
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScreenLayout {
+    ///Width and height of the window
+    pub window_size: Vector2<i32>,
+    pub tile_size: i32,
+    ///Top left corner of the field on screen
+    pub field_origin: Vector2<i32>,
+    ///Width and height of the field in tiles
+    pub field_size: Vector2<usize>,
+    pub shape: CellShape,
+}
+
+impl ScreenLayout {
+    ///Picks the biggest tile size that lets the whole field fit into the window below the panel
+    /// and places the field in the middle of the space that is left
+    pub fn fit(
+        window_size: Vector2<i32>,
+        field_width: usize,
+        field_height: usize,
+        shape: CellShape,
+    ) -> ScreenLayout {
+        let available = Vector2::new(window_size.x, (window_size.y - CONTROLS_PANEL_SIZE).max(0));
+        let (width, height) = (field_width.max(1) as i32, field_height.max(1) as i32);
+        let tile_size = match shape {
+            CellShape::Square => (available.x / width).min(available.y / height),
+            //hex field is half a tile wider and every row after the first adds 3/4 of a tile
+            CellShape::Hex => {
+                (available.x * 2 / (width * 2 + 1)).min(available.y * 4 / (height * 3 + 1))
+            }
+        }
+        .max(MIN_TILE_SIZE);
+        let field_pixels = field_pixels(shape, field_width, field_height, tile_size);
+        ScreenLayout {
+            window_size,
+            tile_size,
+            //if field does not fit even with the smallest tiles, it starts at the corner
+            field_origin: Vector2::new(
+                ((available.x - field_pixels.x) / 2).max(0),
+                CONTROLS_PANEL_SIZE + ((available.y - field_pixels.y) / 2).max(0),
+            ),
+            field_size: Vector2::new(field_width, field_height),
+            shape,
+        }
+    }
+
+    ///Width and height of the field in pixels
+    pub fn field_pixels(&self) -> Vector2<i32> {
+        field_pixels(
+            self.shape,
+            self.field_size.x,
+            self.field_size.y,
+            self.tile_size,
+        )
+    }
+
+    ///Distance between tops of two rows that are next to each other
+    pub fn row_step(&self) -> i32 {
+        match self.shape {
+            CellShape::Square => self.tile_size,
+            CellShape::Hex => self.tile_size * 3 / 4,
+        }
+    }
+
+    ///How far tiles of the row are shifted to the right
+    fn row_shift(&self, row: usize) -> i32 {
+        match self.shape {
+            CellShape::Hex if row % 2 == 1 => self.tile_size / 2,
+            _ => 0,
+        }
+    }
+
+    ///Top left corner of the tile
+    pub fn tile_position(&self, cell: CellCoord) -> Vector2<i32> {
+        Vector2::new(
+            self.field_origin.x + cell.col as i32 * self.tile_size + self.row_shift(cell.row),
+            self.field_origin.y + cell.row as i32 * self.row_step(),
+        )
+    }
+
+    ///Center of the tile
+    pub fn tile_center(&self, cell: CellCoord) -> Vector2<i32> {
+        self.tile_position(cell) + Vector2::new(self.tile_size / 2, self.tile_size / 2)
+    }
+
+    ///Position and size of the button of the tile.
+    /// Buttons are a bit smaller than tiles so that there is a visible gap between them
+    pub fn button_rect(&self, cell: CellCoord) -> (Vector2<i32>, i32) {
+        let padding = self.tile_size / 25;
+        (
+            self.tile_position(cell) + Vector2::new(padding, padding),
+            self.tile_size * 9 / 10,
+        )
+    }
+
+    ///Returns the tile under the given point on screen, None if there is no tile there
+    pub fn tile_at(&self, point: Vector2<i32>) -> Option<CellCoord> {
+        //field can be moved under the panel by the camera and past the edges of the window,
+        //mouse can be released outside of the window after dragging
+        if point.y < CONTROLS_PANEL_SIZE
+            || point.x < 0
+            || point.x >= self.window_size.x
+            || point.y >= self.window_size.y
+        {
+            return None;
+        }
+        let local = point - self.field_origin;
+        let inside = |cell: CellCoord| cell.row < self.field_size.y && cell.col < self.field_size.x;
+        match self.shape {
+            CellShape::Square => {
+                if local.x < 0 || local.y < 0 {
+                    return None;
+                }
+                let cell = CellCoord::new(
+                    (local.x / self.tile_size) as usize,
+                    (local.y / self.tile_size) as usize,
+                );
+                Some(cell).filter(|cell| inside(*cell))
+            }
+            CellShape::Hex => {
+                //rows overlap, so the point can be in the row it's in or in the one above it
+                let row = local.y.div_euclid(self.row_step());
+                (row - 1..=row)
+                    .rev()
+                    .filter(|row| *row >= 0)
+                    .map(|row| {
+                        let column =
+                            (local.x - self.row_shift(row as usize)).div_euclid(self.tile_size);
+                        (column, row)
+                    })
+                    .filter(|(column, _)| *column >= 0)
+                    .map(|(column, row)| CellCoord::new(column as usize, row as usize))
+                    .filter(|cell| inside(*cell))
+                    .find(|cell| is_inside_hex(point - self.tile_center(*cell), self.tile_size / 2))
+            }
+        }
+    }
+
+    ///Returns the tile whose button is under the given point on screen.
+    /// Unlike `tile_at` this ignores the gaps between the buttons, so a click that misses
+    /// every button does nothing instead of hitting the closest tile
+    pub fn hit_test(&self, point: Vector2<i32>) -> Option<CellCoord> {
+        let cell = self.tile_at(point)?;
+        let (position, size) = self.button_rect(cell);
+        let local = point - position;
+        let hit = match self.shape {
+            CellShape::Square => local.x >= 0 && local.y >= 0 && local.x < size && local.y < size,
+            CellShape::Hex => is_inside_hex(local - Vector2::new(size / 2, size / 2), size / 2),
+        };
+        if hit {
+            Some(cell)
+        } else {
+            None
+        }
+    }
+
+    ///Is any part of the tile in the window. Tiles that are partially under the panel
+    /// are not visible either, because they would be drawn on top of it
+    pub fn is_tile_visible(&self, cell: CellCoord) -> bool {
+        let position = self.tile_position(cell);
+        position.y >= CONTROLS_PANEL_SIZE
+            && position.y < self.window_size.y
+            && position.x + self.tile_size > 0
+            && position.x < self.window_size.x
+    }
+
+    ///Position and size of the part of the window below the panel,
+    /// this is what pause cover and settings screen cover
+    pub fn field_area(&self) -> (Vector2<i32>, Vector2<i32>) {
+        (
+            Vector2::new(0, CONTROLS_PANEL_SIZE),
+            Vector2::new(self.window_size.x, self.window_size.y - CONTROLS_PANEL_SIZE),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_layout() -> ScreenLayout {
+        //400x300 below the panel fits 4x3 tiles of 100 pixels exactly
+        ScreenLayout::fit(Vector2::new(400, 400), 4, 3, CellShape::Square)
+    }
+
+    fn hex_layout() -> ScreenLayout {
+        ScreenLayout::fit(Vector2::new(450, 400), 4, 4, CellShape::Hex)
+    }
+
+    #[test]
+    fn square_click_hits_the_button_under_it() {
+        let layout = square_layout();
+        assert_eq!(layout.tile_size, 100);
+        assert_eq!(layout.field_origin, Vector2::new(0, CONTROLS_PANEL_SIZE));
+        for cell in [
+            CellCoord::new(0, 0),
+            CellCoord::new(3, 2),
+            CellCoord::new(2, 1),
+        ] {
+            assert_eq!(layout.tile_at(layout.tile_center(cell)), Some(cell));
+            assert_eq!(layout.hit_test(layout.tile_center(cell)), Some(cell));
+        }
+    }
+
+    #[test]
+    fn square_click_in_the_gap_hits_nothing() {
+        let layout = square_layout();
+        //gap between the first two buttons of the row, it still belongs to the first tile
+        let gap = layout.tile_position(CellCoord::new(1, 0)) + Vector2::new(-2, 50);
+        assert_eq!(layout.tile_at(gap), Some(CellCoord::new(0, 0)));
+        assert_eq!(layout.hit_test(gap), None);
+        //gap between two rows
+        let gap = layout.tile_position(CellCoord::new(1, 1)) + Vector2::new(50, 1);
+        assert_eq!(layout.tile_at(gap), Some(CellCoord::new(1, 1)));
+        assert_eq!(layout.hit_test(gap), None);
+    }
+
+    #[test]
+    fn square_click_on_the_panel_or_outside_of_the_window_hits_nothing() {
+        let mut layout = square_layout();
+        //field moved by the camera, so that the first row is under the panel
+        layout.field_origin.y -= layout.tile_size;
+        let under_panel = layout.tile_center(CellCoord::new(1, 0));
+        assert!(under_panel.y < CONTROLS_PANEL_SIZE);
+        assert_eq!(layout.tile_at(under_panel), None);
+        assert_eq!(layout.hit_test(under_panel), None);
+        assert_eq!(
+            layout.hit_test(Vector2::new(50, CONTROLS_PANEL_SIZE + 50)),
+            Some(CellCoord::new(0, 1))
+        );
+
+        //field moved to the left, so that the last column is past the right edge
+        let mut layout = square_layout();
+        layout.field_origin.x += 50;
+        for point in [
+            Vector2::new(400, 150),
+            Vector2::new(420, 150),
+            Vector2::new(-1, 150),
+            Vector2::new(150, 400),
+        ] {
+            assert_eq!(layout.tile_at(point), None, "{:?}", point);
+            assert_eq!(layout.hit_test(point), None, "{:?}", point);
+        }
+        //last pixel of the window still hits the tile that is cut by the edge
+        assert_eq!(
+            layout.hit_test(Vector2::new(399, 150)),
+            Some(CellCoord::new(3, 0))
+        );
+    }
+
+    #[test]
+    fn hex_click_hits_the_button_under_it() {
+        let layout = hex_layout();
+        for cell in layout_cells(&layout) {
+            assert_eq!(layout.tile_at(layout.tile_center(cell)), Some(cell));
+            assert_eq!(layout.hit_test(layout.tile_center(cell)), Some(cell));
+        }
+    }
+
+    #[test]
+    fn hex_click_in_the_gap_hits_nothing() {
+        let layout = hex_layout();
+        //vertical side that two tiles of the same row share
+        let side =
+            layout.tile_position(CellCoord::new(1, 0)) + Vector2::new(0, layout.tile_size / 2);
+        assert!(layout.tile_at(side).is_some());
+        assert_eq!(layout.hit_test(side), None);
+        //top corner of the field is outside of the first hexagon
+        let corner = layout.tile_position(CellCoord::new(0, 0)) + Vector2::new(1, 1);
+        assert_eq!(layout.tile_at(corner), None);
+        assert_eq!(layout.hit_test(corner), None);
+        //shifted odd row leaves an empty half of a tile on the left
+        let shifted =
+            layout.tile_position(CellCoord::new(0, 1)) + Vector2::new(-2, layout.tile_size / 2);
+        assert_eq!(layout.tile_at(shifted), None);
+        assert_eq!(layout.hit_test(shifted), None);
+    }
+
+    #[test]
+    fn hex_click_on_the_panel_or_outside_of_the_window_hits_nothing() {
+        let mut layout = hex_layout();
+        layout.field_origin.y -= layout.row_step();
+        let under_panel = layout.tile_center(CellCoord::new(1, 0));
+        assert!(under_panel.y < CONTROLS_PANEL_SIZE);
+        assert_eq!(layout.hit_test(under_panel), None);
+        //second row is below the panel and can still be clicked
+        let below_panel = layout.tile_center(CellCoord::new(1, 1));
+        assert!(below_panel.y >= CONTROLS_PANEL_SIZE);
+        assert_eq!(layout.hit_test(below_panel), Some(CellCoord::new(1, 1)));
+
+        let mut layout = hex_layout();
+        layout.field_origin.x = layout.window_size.x - layout.tile_size / 2;
+        let cut_by_edge = layout.tile_center(CellCoord::new(0, 0));
+        assert_eq!(cut_by_edge.x, layout.window_size.x);
+        assert_eq!(layout.tile_at(cut_by_edge), None);
+        assert_eq!(layout.hit_test(cut_by_edge), None);
+        assert_eq!(
+            layout.hit_test(cut_by_edge - Vector2::new(1, 0)),
+            Some(CellCoord::new(0, 0))
+        );
+        let below_window = Vector2::new(10, layout.window_size.y);
+        assert_eq!(layout.hit_test(below_window), None);
+    }
+
+    fn layout_cells(layout: &ScreenLayout) -> Vec<CellCoord> {
+        (0..layout.field_size.y)
+            .flat_map(|row| (0..layout.field_size.x).map(move |col| CellCoord::new(col, row)))
+            .collect()
+    }
+}
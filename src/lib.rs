@@ -0,0 +1,47 @@
+/**This file contains the modules of the game, they are a library
+ * so that both the game and the benchmarks can use them
+ */
+pub mod assets;
+pub mod board_renderer;
+pub mod camera;
+pub mod clock;
+pub mod controls;
+pub mod coop;
+pub mod events;
+pub mod fallback_art;
+pub mod gameplay;
+pub mod grid;
+pub mod layout;
+pub mod minesweeper_ui;
+pub mod neighborhood;
+pub mod network;
+pub mod options;
+pub mod race;
+pub mod render;
+pub mod rules;
+pub mod save;
+pub mod settings;
+
+#[derive(Default, Clone, Debug)]
+pub struct Field {
+    pub value: i32,
+    ///Number of mines in the tile, only multi-mine fields have tiles with more than one
+    pub bombs: u32,
+    pub border: bool,
+    pub revealed: bool,
+    ///Number of flags player placed on the tile
+    pub flags: u32,
+    pub questioned: bool,
+    ///Player that placed the flags or the question mark, only set in co-op games
+    pub owner: Option<usize>,
+}
+
+impl Field {
+    pub fn has_bomb(&self) -> bool {
+        self.bombs > 0
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        self.flags > 0
+    }
+}
@@ -1,394 +1,356 @@
-use game_oxide_framework::*;
-use game_oxide_framework::{components::*, render::*, texture_manager::*};
-use nalgebra::{Vector2, Vector4};
-use sdl2::event::Event;
+use game_oxide_framework::components::*;
+use game_oxide_framework::{layers, setup, ui};
+use nalgebra::Vector2;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::render::Canvas;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::Window;
+use sdl2::{EventPump, VideoSubsystem};
 use specs::{
-    Builder, Component, DispatcherBuilder, Entity, NullStorage, VecStorage, World, WorldExt,
+    Builder, Component, Dispatcher, DispatcherBuilder, Entity, NullStorage, World, WorldExt,
 };
 
-use rand::distributions::{Distribution, Uniform};
-use std::time::SystemTime;
-pub mod assets;
-pub mod minesweeper_ui;
-use minesweeper_ui::*;
-
-///Defines the parent of the drop down menu items. this is the thing that gets unwrapped
-#[derive(Clone, Debug, PartialEq, Component, Default)]
-#[storage(VecStorage)]
-pub struct Tile {
-    pub position: Vector2<usize>,
-    pub revealed: bool,
-}
-#[derive(Default, Clone)]
-struct Field {
-    value: i32,
-    bomb: bool,
-    border: bool,
-    revealed: bool,
-    flagged: bool,
-}
+use minesweeper_ecs::assets::{AssetError, Theme};
+use minesweeper_ecs::board_renderer::*;
+use minesweeper_ecs::camera::*;
+use minesweeper_ecs::clock::*;
+use minesweeper_ecs::controls::*;
+use minesweeper_ecs::coop::{CoopClient, PlayerCursors};
+use minesweeper_ecs::events::*;
+use minesweeper_ecs::gameplay::*;
+use minesweeper_ecs::grid::*;
+use minesweeper_ecs::layout::*;
+use minesweeper_ecs::minesweeper_ui::*;
+use minesweeper_ecs::network::CoopAction;
+use minesweeper_ecs::options::*;
+use minesweeper_ecs::race::RaceClient;
+use minesweeper_ecs::render::{render_frame, TextureAtlas};
+use minesweeper_ecs::rules::*;
+use minesweeper_ecs::save::*;
+use minesweeper_ecs::settings::*;
+use minesweeper_ecs::{assets, coop, fallback_art, race, Field};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::Duration;
 
 #[derive(Component, Default, Clone)]
 #[storage(NullStorage)]
 struct FaceButton;
 
-enum GameState {
-    ///Game is still happening
-    Active,
-    ///Player is browsing menues
-    Setup,
-    ///Player has either won or lost
-    Ended,
+///Position of the mine display, it is on the right side of the panel
+fn mine_display_position(layout: &ScreenLayout, digits: usize) -> Vector2<i32> {
+    Vector2::new(
+        layout.window_size.x - DISPLAY_MARGIN - digits as i32 * SEGMENTED_DIGIT_WIDTH,
+        10,
+    )
 }
 
-fn generate_grid(area_size: usize, bomb_count: u32) -> Vec<Vec<Field>> {
-    let mut rng = rand::thread_rng();
-    //generate default grid
-    let mut grid: Vec<Vec<Field>> = vec![vec![Field::default(); area_size]; area_size];
-    let mut bombs: Vec<Vector2<usize>> = Vec::new();
-
-    let mut bomb_count = bomb_count;
-    let die = Uniform::from(0..area_size);
-
-    //generate all bombs
-    //we simply pick a random position and then if the position is taken
-    // we move diagonally to the left bottom until we hit a good spot
-    //if we reach the corner we move the line down and start from almost top right corner doing the same thing
-    while bomb_count > 0 {
-        let mut point: Vector2<usize> = Vector2::new(die.sample(&mut rng), die.sample(&mut rng));
-        while grid[point.x][point.y].bomb {
-            //to prevent going over the border because line was randomly generated
-            if point.x >= area_size || point.y >= area_size {
-                point.x = 1;
-                point.y = 0;
-            } else {
-                point.x += 1;
-                point.y += 1
-            }
-        }
-        grid[point.x][point.y].bomb = true;
-        bombs.push(point);
-        bomb_count -= 1;
-    }
-    //calculate values for bombs
-    for i in 0..area_size {
-        for j in 0..area_size {
-            if grid[i][j].bomb {
-                //this weird loop below just represents going over a 3x3 square
-                //with checks to prevent going over borders
-                for a in -1i32..=1 {
-                    let vert: i32 = i as i32 + a;
-                    if vert >= area_size as i32 || vert < 0 {
-                        continue;
-                    }
-                    for b in -1i32..=1 {
-                        let hor: i32 = j as i32 + b;
-                        if hor >= area_size as i32 || hor < 0 {
-                            continue;
-                        }
-                        //we ignore the fact that the tile is a bomb and still add value
-                        //because bomb with value is still a bomb, duh
-                        grid[vert as usize][hor as usize].value += 1;
-                        //mark this tile as border
-                        if !grid[vert as usize][hor as usize].bomb {
-                            grid[vert as usize][hor as usize].border = true;
-                        }
-                    }
-                }
-            }
-        }
-    }
-    grid
+///Position of the rule set label, it is under the face
+fn rule_set_label_position(layout: &ScreenLayout) -> Vector2<i32> {
+    Vector2::new(
+        layout.window_size.x / 2 - RULE_SET_LABEL_SIZE.x / 2,
+        CONTROLS_PANEL_SIZE - RULE_SET_LABEL_SIZE.y - 2,
+    )
 }
 
-///Reveal the tile and all neighboring 0 tiles using flood algorithm
-fn reveal_block(
-    point: Vector2<i32>,
-    grid: &mut Vec<Vec<Field>>,
-    world: &mut World,
-    buttons: &Vec<Vec<Entity>>,
-) {
-    //simple border check
-    if point.x < 0 || point.x >= grid.len() as i32 || point.y < 0 || point.y >= grid.len() as i32 {
-        return;
-    }
-    let x = point.x as usize;
-    let y = point.y as usize;
-    //don't reveal bombs
-    if grid[x][y].bomb || grid[x][y].revealed {
-        return;
-    }
-    //update tile state so it would be drawn
-    {
-        if let Some(tile) = world.write_component::<Tile>().get_mut(buttons[x][y]) {
-            tile.revealed = true;
-        }
-        if let Some(text) = world.write_component::<Text>().get_mut(buttons[x][y]) {
-            text.visible = true;
+///Window can not be narrower than this, otherwise displays and the face would overlap
+const MIN_WINDOW_WIDTH: i32 = 450;
+///Space between the displays and the borders of the window
+const DISPLAY_MARGIN: i32 = 50;
+
+///Network game that the window takes part in
+enum Network {
+    ///Nobody else plays this game
+    Offline,
+    Race(RaceClient),
+    Coop(CoopClient),
+    ///Server or bot has already run until the end, so there is no window to open
+    Finished,
+}
+
+///Runs the server or the bot if options ask for them, otherwise joins the network game if there is one.
+/// Server and host make the field of the network game, everyone else gets it from them
+fn start_network(
+    options: &Options,
+    settings: &Settings,
+    options_grid: &Grid<Field>,
+    rng: &mut StdRng,
+) -> Result<Network, String> {
+    let mut make_board = || {
+        let grid = generate_grid(
+            options_grid.clone(),
+            options.mines,
+            options.mines_per_cell,
+            rng,
+        );
+        Board::new(grid, options.mines, options.mines_per_cell)
+    };
+    if let Some(port) = options.server {
+        let board = make_board();
+        if options.coop {
+            coop::run_server(
+                port,
+                options.players,
+                board,
+                GameRules::from_settings(settings),
+            )?;
+        } else {
+            race::run_server(port, options.players, board_layout(&board))?;
         }
-        if let Some(sprite) = world.write_component::<Sprite>().get_mut(buttons[x][y]) {
-            if grid[x][y].bomb {
-                sprite.name = "tile_bomb".to_owned();
-            } else {
-                sprite.name = "tile_".to_owned() + grid[x][y].value.to_string().as_str();
-            }
+        return Ok(Network::Finished);
+    }
+    if let Some(port) = options.host {
+        let board = make_board();
+        if options.coop {
+            coop::host(
+                port,
+                options.players,
+                board,
+                GameRules::from_settings(settings),
+            )?;
+        } else {
+            race::host(port, options.players, board_layout(&board))?;
         }
-        //once we reveal tile it stops being a button
-        world.write_component::<ui::Button>().remove(buttons[x][y]);
-        //mark tile as visited
-        grid[x][y].revealed = true;
     }
-    //if this is a border we want to display the tile itself, but not go any further
-    if grid[x][y].border {
-        return;
+    //host joins its own game like everyone else
+    let address = match (options.host, &options.join) {
+        (Some(port), _) => format!("127.0.0.1:{}", port),
+        (_, Some(address)) => address.clone(),
+        _ => return Ok(Network::Offline),
+    };
+    if options.bot {
+        coop::run_bot(&address, &options.name, options.seed)?;
+        Ok(Network::Finished)
+    } else if options.coop {
+        Ok(Network::Coop(CoopClient::join(&address, &options.name)?))
+    } else {
+        Ok(Network::Race(RaceClient::join(&address, &options.name)?))
     }
-    //reveal neighbors
-    reveal_block(point + Vector2::new(0, -1), grid, world, buttons);
-    reveal_block(point + Vector2::new(-1, 0), grid, world, buttons);
-    reveal_block(point + Vector2::new(0, 1), grid, world, buttons);
-    reveal_block(point + Vector2::new(1, 0), grid, world, buttons);
 }
 
-///Attempts to flag a block
-/// If fails returns false
-fn flag_block(
-    point: Vector2<i32>,
-    grid: &mut Vec<Vec<Field>>,
-    world: &mut World,
-    buttons: &Vec<Vec<Entity>>,
-    flag_count: &mut i32,
-    total_mine_count: i32,
-) -> bool {
-    if point.x < 0 || point.x >= grid.len() as i32 || point.y < 0 || point.y >= grid.len() as i32 {
-        return false;
+///Replays what player did before saving the game
+fn restore_save(board: &mut Board, save: &SaveGame) {
+    for position in &save.revealed {
+        reveal_block(
+            CellCoord::from_row_col(*position),
+            &mut board.grid,
+            &mut Vec::new(),
+        );
+    }
+    let (total_mine_count, mines_per_cell) = (board.total_mine_count, board.mines_per_cell);
+    for position in &save.flagged {
+        flag_block(
+            CellCoord::from_row_col(*position),
+            &mut board.grid,
+            &mut board.flag_count,
+            total_mine_count as i32,
+            mines_per_cell,
+            //flags are placed by the save, so the rules of the current settings don't matter
+            &GameRules {
+                allow_over_flagging: true,
+                question_marks: false,
+                ..GameRules::default()
+            },
+        );
     }
+}
 
-    let x = point.x as usize;
-    let y = point.y as usize;
-    //if tile was revealed then we either know it's not a bomb or we lost the game
-    //no point in flagging it either way
-    if grid[x][y].revealed {
-        return false;
+///Saves unfinished game so that it can be continued with --load
+fn autosave(world: &World, state: GameState) {
+    let board = world.read_resource::<Board>();
+    let unfinished = state != GameState::Ended && board.grid.values().any(|field| field.revealed);
+    if !unfinished {
+        return;
     }
-    if !grid[x][y].flagged && *flag_count >= total_mine_count {
-        return false;
+    let save = SaveGame {
+        board: board_layout(&board),
+        revealed: collect_positions(&board.grid, |field| field.revealed),
+        flagged: collect_counts(&board.grid, |field| field.flags),
+        time_ms: world.read_resource::<GameClock>().elapsed().as_millis() as u64,
+    };
+    match save.save(AUTOSAVE_PATH) {
+        Ok(()) => println!("Game saved to {}", AUTOSAVE_PATH),
+        Err(e) => println!("{}", e),
     }
+}
 
-    if let Some(sprite) = world.write_component::<Sprite>().get_mut(buttons[x][y]) {
-        if grid[x][y].flagged {
-            sprite.name = "tile_default".to_owned();
-        } else {
-            sprite.name = "tile_flag".to_owned();
-        }
+///Saves the game that has just ended as the last replay
+fn save_replay(world: &World, actions: &[ReplayAction], hot_seat: bool) {
+    let recording = Replay {
+        board: board_layout(&world.read_resource::<Board>()),
+        actions: actions.to_vec(),
+        time_ms: world.read_resource::<GameClock>().elapsed().as_millis() as u64,
+        hot_seat,
+    };
+    if let Err(e) = recording.save(LAST_REPLAY_PATH) {
+        println!("{}", e);
     }
-    if let Some(button) = world.write_component::<ui::Button>().get_mut(buttons[x][y]) {
-        if !grid[x][y].flagged {
-            button.hovered_over_texture_name = Some("tile_flag".to_owned());
-            button.normal_texture_name = Some("tile_flag".to_owned());
-        } else {
-            button.hovered_over_texture_name = Some("tile_selected".to_owned());
-            button.normal_texture_name = Some("tile_default".to_owned());
-        }
+}
+
+///Kind of the action that is recorded into the replay, None if action is not recorded
+fn replay_action_kind(action: GameAction) -> Option<ReplayActionKind> {
+    match action {
+        GameAction::Reveal => Some(ReplayActionKind::Reveal),
+        GameAction::Flag => Some(ReplayActionKind::Flag),
+        GameAction::Chord => Some(ReplayActionKind::Chord),
+        GameAction::Hint => Some(ReplayActionKind::Hint),
+        _ => None,
     }
-    grid[x][y].flagged = !grid[x][y].flagged;
-    *flag_count += if grid[x][y].flagged { 1 } else { -1 };
-    return true;
 }
 
-fn end_game(
-    win: bool,
-    world: &mut World,
-    face: &Entity,
-    buttons: &Vec<Vec<Entity>>,
-    grid: &Vec<Vec<Field>>,
-    area_size: usize,
-) {
-    if let Some(button) = world.write_component::<ui::Button>().get_mut(*face) {
-        button.normal_texture_name = if win {
-            Some("face_win".to_owned())
-        } else {
-            Some("face_loose".to_owned())
+///Returns actions of the replay that were made by `elapsed_ms` and moves `next_action` past them.
+/// Actions on tiles that are not on the field are skipped
+fn due_replay_actions(
+    replay: &Replay,
+    next_action: &mut usize,
+    elapsed_ms: u64,
+    grid: &Grid<Field>,
+) -> Vec<(GameAction, CellCoord)> {
+    let mut actions: Vec<(GameAction, CellCoord)> = Vec::new();
+    while let Some(recorded) = replay.actions.get(*next_action) {
+        if recorded.time_ms > elapsed_ms {
+            break;
         }
-    }
-    //TODO: Replace with actual code
-    if !win {
-        //reveal all bombs
-        for i in 0..area_size {
-            for j in 0..area_size {
-                if grid[i][j].bomb {
-                    //update tile state so it would be drawn
-                    {
-                        if let Some(tile) = world.write_component::<Tile>().get_mut(buttons[i][j]) {
-                            tile.revealed = true;
-                        }
-                        if let Some(text) = world.write_component::<Text>().get_mut(buttons[i][j]) {
-                            text.visible = true;
-                        }
-                        if let Some(sprite) =
-                            world.write_component::<Sprite>().get_mut(buttons[i][j])
-                        {
-                            sprite.name = "tile_bomb".to_owned();
-                        }
-                        //once we reveal tile it stops being a button
-                        world.write_component::<ui::Button>().remove(buttons[i][j]);
-                    }
-                }
-            }
+        let action = match recorded.kind {
+            ReplayActionKind::Reveal => GameAction::Reveal,
+            ReplayActionKind::Flag => GameAction::Flag,
+            ReplayActionKind::Chord => GameAction::Chord,
+            ReplayActionKind::Hint => GameAction::Hint,
+        };
+        //replays store the row first
+        let cell = match recorded.position {
+            [row, col] if row >= 0 && col >= 0 => Some(CellCoord::new(col as usize, row as usize)),
+            _ => None,
+        };
+        if let Some(cell) = cell.filter(|cell| grid.contains(*cell)) {
+            actions.push((action, cell));
         }
+        *next_action += 1;
     }
+    actions
 }
 
-///Checks if all mines have been flagged
-fn check_mines(grid: &mut Vec<Vec<Field>>, area_size: usize) -> bool {
-    for i in 0..area_size {
-        for j in 0..area_size {
-            if grid[i][j].bomb && !grid[i][j].flagged {
-                return false;
-            }
+///Makes the window resizable and shrinks it if it does not fit on the screen,
+/// huge fields are looked around with the camera. Returns the size window ended up with
+fn setup_window(
+    canvas: &mut Canvas<Window>,
+    video_subsystem: &VideoSubsystem,
+    size: Vector2<i32>,
+    min_size: Vector2<i32>,
+) -> Result<Vector2<i32>, String> {
+    let mut size = size;
+    if let Ok(bounds) = video_subsystem.display_usable_bounds(0) {
+        if size.x > bounds.width() as i32 || size.y > bounds.height() as i32 {
+            size.x = size.x.min(bounds.width() as i32);
+            size.y = size.y.min(bounds.height() as i32);
+            canvas
+                .window_mut()
+                .set_size(size.x as u32, size.y as u32)
+                .map_err(|e| e.to_string())?;
         }
     }
-    true
+    //window is created by the framework and sdl2 crate has no way to make existing window resizable
+    unsafe {
+        sdl2::sys::SDL_SetWindowResizable(canvas.window().raw(), sdl2::sys::SDL_bool::SDL_TRUE);
+    }
+    canvas
+        .window_mut()
+        .set_minimum_size(min_size.x as u32, min_size.y as u32)
+        .map_err(|e| e.to_string())?;
+    Ok(size)
 }
 
-fn make_text_box(
-    world: &mut World,
-    default_text: String,
-    layer: layers::RenderLayers,
-    location: Vector2<i32>,
-) -> Entity {
-    world
-        .create_entity()
-        .with(Position {
-            x: location.x,
-            y: location.y,
-        })
-        .with(Rectangle {
-            width: 100,
-            height: 50,
-        })
-        .with(Colored {
-            color: sdl2::pixels::Color::RGB(0, 0, 0),
-        })
-        .with(Text {
-            text: default_text,
-            color: sdl2::pixels::Color::RED,
-            visible: true,
-            offset: Vector2::new(10, 0),
-        })
-        .with(Renderable::new(true, layer as u32))
+///Systems of the game in the order they run
+fn make_dispatcher<'a, 'b>(world: &mut World) -> Dispatcher<'a, 'b> {
+    DispatcherBuilder::new()
+        .with(FlagSystem, "flag_system", &[])
+        .with(RevealSystem, "reveal_system", &["flag_system"])
+        .with(
+            HotSeatSystem::new(world),
+            "hot_seat_system",
+            &["reveal_system"],
+        )
+        .with(WinLossSystem, "win_loss_system", &["hot_seat_system"])
+        .with(TimerSystem::default(), "timer_system", &["win_loss_system"])
+        .with(
+            DisplaySyncSystem::new(world),
+            "display_sync_system",
+            &["timer_system"],
+        )
+        //face gets its texture from the display sync, so buttons are updated after it
+        .with(
+            ui::ButtonUpdateSystem,
+            "button_update_system",
+            &["display_sync_system"],
+        )
         .build()
 }
 
-fn generate_game(
-    world: &mut World,
-    buttons: &mut Vec<Vec<Entity>>,
-    mine_count: u32,
-    area_size: usize,
-    controls_panel_size: i32,
-    bomb_display: &Vec<Entity>,
-) -> Result<Vec<Vec<Field>>, String> {
-    let mut grid = generate_grid(10, mine_count);
-
-    if buttons.len() > 0 {
-        for i in 0..area_size {
-            for j in 0..area_size {
-                if buttons[i].len() > 0 {
-                    world
-                        .delete_entity(buttons[i][j])
-                        .map_err(|e| e.to_string())?;
-                }
+///Loads the font from the assets or the system, failure is added to the asset errors
+fn load_font<'ttf>(
+    ttf_context: &'ttf Sdl2TtfContext,
+    asset_errors: &mut Vec<AssetError>,
+) -> Option<Font<'ttf, 'static>> {
+    match assets::find_font() {
+        Ok(path) => match ttf_context.load_font(&path, 50) {
+            Ok(font) => Some(font),
+            Err(reason) => {
+                asset_errors.push(AssetError::Malformed {
+                    name: "Font".to_owned(),
+                    path,
+                    reason,
+                });
+                None
             }
+        },
+        Err(e) => {
+            asset_errors.push(e);
+            None
         }
     }
-    buttons.clear();
-
-    //generate button entities
-    for i in 0..area_size {
-        buttons.push(Vec::new());
-        for j in 0..area_size {
-            buttons[i].push(
-                ui::make_button_base(
-                    world,
-                    Vector2::new(j as i32 * 50 + 2, i as i32 * 50 + 2 + controls_panel_size),
-                    Vector2::new(45, 45),
-                    Some(ui::Button {
-                        hovered_over: false,
-                        hovered_over_texture_name: Some("tile_selected".to_owned()),
-                        hovered_over_text: None,
-                        hovered_over_color: Some(sdl2::pixels::Color::RGBA(255, 0, 255, 120)),
-                        normal_texture_name: Some("tile_default".to_owned()),
-                        normal_text: None,
-                        normal_color: Some(sdl2::pixels::Color::RGBA(
-                            if grid[i][j].border { 0 } else { 255 },
-                            255,
-                            255,
-                            120,
-                        )),
-                    }),
-                    sdl2::pixels::Color::RGBA(255, 255, 255, 120),
-                    layers::RenderLayers::Menu,
-                )
-                .with(Tile {
-                    position: Vector2::new(i, j),
-                    revealed: false,
-                })
-                .with(Sprite {
-                    name: "tile_default".to_owned(),
-                    source_rect: Some(Vector4::new(0, 0, 16, 16)),
-                    size: Vector2::new(50, 50),
-                    visible: true,
-                })
-                .build(),
-            );
+}
+
+///Loads the theme with the given name, if some of it fails to load default theme is loaded instead.
+/// Textures are replaced in place, so everything that uses them changes look on the next frame
+fn apply_theme<T>(
+    atlas: &mut TextureAtlas<T>,
+    themes: &[Theme],
+    name: &str,
+    fallback_theme: Option<&Theme>,
+    window: &Window,
+) -> Result<(), String> {
+    let theme = match themes.iter().find(|theme| theme.name == name) {
+        Some(theme) => theme,
+        None => return Ok(()),
+    };
+    if let Err(errors) = assets::load_textures(atlas, theme, fallback_theme) {
+        println!("Failed to load theme {}:", theme.name);
+        for error in &errors {
+            println!("{}", error);
+        }
+        if let Err(errors) = assets::load_textures(atlas, &themes[0], fallback_theme) {
+            return Err(assets::show_errors(window, &errors));
         }
     }
-    update_segmented_display(world, bomb_display, mine_count);
-    Ok(grid)
+    Ok(())
 }
 
-fn main() -> Result<(), String> {
-    let area_size: usize = 10;
-    let controls_panel_size: u32 = 100;
-    let (mut world, sdl, video_subsystem, ttf_context, mut canvas, mut game) = setup::setup(
-        "Rust Minesweeper by MetalPizzaCat".to_owned(),
-        Some(Vector2::new(
-            area_size as u32 * 50,
-            area_size as u32 * 50 + controls_panel_size,
-        )),
-    )?;
-    let mut dispatcher = DispatcherBuilder::new()
-        .with(ui::ButtonUpdateSystem, "button_update_system", &[])
-        .build();
-    ui::register_ui_components(&mut world);
-    let mut event_pump = sdl.event_pump().unwrap();
-
-    let mut texture_creator = canvas.texture_creator();
-    let mut texture_manager = TextureManager::new(&texture_creator)?;
-    assets::load_textures(&mut texture_manager)?;
-    //register components necessary for ECS world to function
-    world.register::<Tile>();
-    world.register::<FaceButton>();
-    world.insert(ui::MouseData::default());
-    let font = ttf_context
-        .load_font("./assets/fonts/Roboto-Medium.ttf", 50)
-        .unwrap();
-
-    let mut buttons: Vec<Vec<Entity>> = Vec::new();
-
-    //game variables
-    let total_mine_count = 10;
-    let mut mines_left = total_mine_count;
-    let mut flag_count: i32 = 0;
-    let mut time: i32 = 0;
-    let mut current_state: GameState = GameState::Active;
-
-    
-    world
+///Makes the panel above the field with the face and the displays, they are stored in `PanelEntities`.
+/// Returns the background of the panel and the rule set label, which only have to be moved on resize
+fn make_panel(
+    world: &mut World,
+    layout: &ScreenLayout,
+    mine_display_digits: usize,
+    time_display_digits: usize,
+    rule_set_name: String,
+    hot_seat: bool,
+) -> (Entity, Entity) {
+    let background = world
         .create_entity()
         .with(Position { x: 0, y: 0 })
         .with(Rectangle {
-            width: area_size as i32 * 50,
-            height: controls_panel_size as i32,
+            width: layout.window_size.x,
+            height: CONTROLS_PANEL_SIZE,
         })
         .with(Colored {
             color: sdl2::pixels::Color::RGB(192, 192, 192),
@@ -397,11 +359,8 @@ fn main() -> Result<(), String> {
         .build();
 
     let face = ui::make_button_base(
-        &mut world,
-        Vector2::new(
-            area_size as i32 * 50 / 2 - 25,
-            controls_panel_size as i32 / 2 - 25,
-        ),
+        world,
+        Vector2::new(layout.window_size.x / 2 - 25, CONTROLS_PANEL_SIZE / 2 - 25),
         Vector2::new(50, 50),
         Some(ui::Button {
             hovered_over: false,
@@ -424,99 +383,719 @@ fn main() -> Result<(), String> {
     })
     .build();
 
-    let mine_display =
-        make_segmented_display(&mut world, Vector2::new(area_size as i32 * 50 - 200, 10));
-    let time_display = make_segmented_display(&mut world, Vector2::new(50, 10));
-    let mut grid: Vec<Vec<Field>> = generate_game(
-        &mut world,
-        &mut buttons,
-        total_mine_count,
-        area_size,
-        controls_panel_size as i32,
-        &mine_display
+    let mine_display = make_segmented_display(
+        world,
+        mine_display_position(layout, mine_display_digits),
+        mine_display_digits,
+    );
+    let time_display =
+        make_segmented_display(world, Vector2::new(DISPLAY_MARGIN, 10), time_display_digits);
+    //shows which tiles count as neighbors, so player knows what the numbers mean
+    let rule_set_label = make_rule_set_label(world, rule_set_label_position(layout), rule_set_name);
+    //when players take turns their scores are shown in place of the time and mines left
+    let score_displays = if hot_seat {
+        set_segmented_display_visible(world, &time_display, false);
+        set_segmented_display_visible(world, &mine_display, false);
+        vec![
+            make_segmented_display(world, Vector2::new(DISPLAY_MARGIN, 10), time_display_digits),
+            make_segmented_display(
+                world,
+                mine_display_position(layout, time_display_digits),
+                time_display_digits,
+            ),
+        ]
+    } else {
+        Vec::new()
+    };
+    world.insert(PanelEntities {
+        face,
+        mine_display,
+        time_display,
+        score_displays,
+    });
+    (background, rule_set_label)
+}
+
+///Moves everything on the panel to fit the new size of the window,
+/// displays on the right side stay next to the border
+fn resize_panel(
+    world: &mut World,
+    layout: &ScreenLayout,
+    background: Entity,
+    rule_set_label: Entity,
+) {
+    let panel = (*world.read_resource::<PanelEntities>()).clone();
+    if let Some(rectangle) = world.write_component::<Rectangle>().get_mut(background) {
+        rectangle.width = layout.window_size.x;
+    }
+    if let Some(position) = world.write_component::<Position>().get_mut(panel.face) {
+        position.x = layout.window_size.x / 2 - 25;
+    }
+    if let Some(position) = world.write_component::<Position>().get_mut(rule_set_label) {
+        position.x = rule_set_label_position(layout).x;
+    }
+    move_segmented_display(
+        world,
+        &panel.mine_display,
+        mine_display_position(layout, panel.mine_display.len()),
+    );
+    if let Some(display) = panel.score_displays.get(1) {
+        move_segmented_display(world, display, mine_display_position(layout, display.len()));
+    }
+}
+
+///What happened to the window during the frame, besides the input of the player
+#[derive(Default)]
+struct WindowChanges {
+    quit: bool,
+    ///Newest size of the window if it was resized
+    resized: Option<Vector2<i32>>,
+}
+
+///Reads the events of the frame and turns them into input of the player.
+/// Mouse data is kept up to date and the keyboard cursor follows the mouse
+fn poll_events<T: TimeSource>(
+    event_pump: &mut EventPump,
+    input_mapper: &mut InputMapper<T>,
+    world: &World,
+    view: &ScreenLayout,
+    cursor: &mut KeyboardCursor,
+    state: GameState,
+) -> (Vec<MappedInput>, WindowChanges) {
+    let mut inputs: Vec<MappedInput> = Vec::new();
+    let mut changes = WindowChanges::default();
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit { .. } => {
+                changes.quit = true;
+                return (inputs, changes);
+            }
+            //player switched to another window, so the game should not keep going
+            Event::Window {
+                win_event: WindowEvent::FocusLost,
+                ..
+            } if state == GameState::Active => {
+                inputs.push(MappedInput {
+                    action: GameAction::Pause,
+                    mouse_position: None,
+                });
+            }
+            Event::Window {
+                win_event: WindowEvent::Resized(new_width, new_height),
+                ..
+            } => changes.resized = Some(Vector2::new(new_width, new_height)),
+            Event::MouseMotion { x, y, .. } => {
+                *world.write_resource::<ui::MouseData>() = ui::MouseData { x, y };
+                //keep keyboard cursor under the mouse so that switching between the two feels natural
+                if let Some(tile) = view.tile_at(Vector2::new(x, y)) {
+                    cursor.position = tile;
+                }
+            }
+            _ => {}
+        }
+        inputs.extend(input_mapper.map_event(&event));
+    }
+    inputs.extend(input_mapper.update());
+    (inputs, changes)
+}
+
+///Actions of the frame, sorted by what they are applied to
+#[derive(Default)]
+struct FrameActions {
+    ///Actions on the tiles and on the game itself
+    game: Vec<(GameAction, CellCoord)>,
+    ///Camera actions come with the point on screen that stays in place while zooming
+    camera: Vec<(GameAction, Vector2<i32>)>,
+    ///Settings clicked on the settings screen, true if they are changed forward
+    settings: Vec<(SettingKind, bool)>,
+}
+
+///Turns input of the player into actions. Mouse actions are applied to the tile under the mouse,
+/// keyboard ones to the cursor. While the settings screen is open only settings can be changed
+fn map_inputs(
+    world: &World,
+    inputs: Vec<MappedInput>,
+    state: GameState,
+    view: &ScreenLayout,
+    cursor: &KeyboardCursor,
+) -> FrameActions {
+    let mut actions = FrameActions::default();
+    for input in inputs {
+        if state == GameState::Setup {
+            //settings screen covers the field, so the only thing that can be clicked is settings
+            if let Some(position) = input.mouse_position {
+                let clicked = ui::get_overlapping_component_with_type::<SettingsButton>(
+                    position,
+                    world.system_data(),
+                )
+                .and_then(|entity| {
+                    world
+                        .read_component::<SettingsButton>()
+                        .get(entity)
+                        .map(|button| button.kind)
+                });
+                match (clicked, input.action) {
+                    (Some(SettingKind::Close), GameAction::Reveal) => actions
+                        .game
+                        .push((GameAction::Settings, CellCoord::default())),
+                    (Some(kind), GameAction::Reveal | GameAction::Flag) => {
+                        actions
+                            .settings
+                            .push((kind, input.action == GameAction::Reveal));
+                    }
+                    _ => {}
+                }
+            } else if matches!(input.action, GameAction::Settings | GameAction::Pause) {
+                actions
+                    .game
+                    .push((GameAction::Settings, CellCoord::default()));
+            }
+            continue;
+        }
+        //camera is not moved by the tiles, so it is not given one
+        if matches!(
+            input.action,
+            GameAction::Pan(_) | GameAction::ZoomIn | GameAction::ZoomOut
+        ) {
+            let (area_position, area_size) = view.field_area();
+            let anchor = input
+                .mouse_position
+                .unwrap_or(area_position + area_size / 2);
+            actions.camera.push((input.action, anchor));
+            continue;
+        }
+        match input.mouse_position {
+            Some(position) => {
+                if ui::get_overlapping_component_with_type::<FaceButton>(
+                    position,
+                    world.system_data(),
+                )
+                .is_some()
+                {
+                    if input.action == GameAction::Reveal {
+                        actions
+                            .game
+                            .push((GameAction::Restart, CellCoord::default()));
+                    }
+                    continue;
+                }
+                //clicks on the panel, around the field and between the tiles are ignored
+                if let Some(tile) = view.hit_test(position) {
+                    actions.game.push((input.action, tile));
+                }
+            }
+            None => actions.game.push((input.action, cursor.position)),
+        }
+    }
+    actions
+}
+
+///Tells other players of the race about the progress and the result, title shows how everyone is doing
+fn update_race(
+    race: &mut RaceClient,
+    world: &World,
+    events: &[GameEvent],
+    window: &mut Window,
+) -> Result<(), String> {
+    if events
+        .iter()
+        .any(|event| matches!(event, GameEvent::CellRevealed(_)))
+    {
+        race.send_progress(&world.read_resource::<Board>());
+    }
+    if events
+        .iter()
+        .any(|event| matches!(event, GameEvent::GameWon | GameEvent::GameLost))
+    {
+        race.send_result(
+            events.contains(&GameEvent::GameWon),
+            world.read_resource::<GameClock>().elapsed().as_millis() as u64,
+        );
+    }
+    if race.update() {
+        window
+            .set_title(&race.status_text())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+///Sends the cursor to the server and takes the changes of the field from it,
+/// they are sent as events the same way gameplay systems send them
+fn update_coop(
+    coop: &mut CoopClient,
+    world: &World,
+    cursor: CellCoord,
+    window: &mut Window,
+) -> Result<(), String> {
+    coop.send_cursor(cursor);
+    let events = coop.update(&mut world.write_resource::<Board>());
+    if events
+        .iter()
+        .any(|event| matches!(event, GameEvent::CellRevealed(_)))
+    {
+        world.write_resource::<GameClock>().start();
+    }
+    world.write_resource::<GameEvents>().iter_write(events);
+    world.write_resource::<PlayerCursors>().cursors = coop.cursors.clone();
+    let title = coop.status_text();
+    if title != window.title() {
+        window.set_title(&title).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), String> {
+    let mut settings = Settings::load();
+    let options = Options::parse(std::env::args(), settings.default_options())?;
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let save = options.load.as_deref().map(SaveGame::load).transpose()?;
+    let replay = options.replay.as_deref().map(Replay::load).transpose()?;
+    let mut rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let options_grid = Grid::with_shape(
+        options.width,
+        options.height,
+        options.shape,
+        Field::default(),
+    )
+    .with_topology(options.topology)
+    .with_neighborhood(options.neighborhood.clone());
+    let (mut race, mut coop) = match start_network(&options, &settings, &options_grid, &mut rng)? {
+        Network::Offline => (None, None),
+        Network::Race(race) => (Some(race), None),
+        Network::Coop(coop) => (None, Some(coop)),
+        Network::Finished => return Ok(()),
+    };
+    //if game is loaded from file or played over the network, field is taken from there
+    let loaded_board = save
+        .as_ref()
+        .map(|save| &save.board)
+        .or(replay.as_ref().map(|replay| &replay.board))
+        .or(race.as_ref().map(|race| &race.board))
+        .or(coop.as_ref().map(|coop| &coop.board));
+    let (empty, total_mine_count, mines_per_cell) = match loaded_board {
+        Some(board) => (
+            empty_grid(board),
+            board.mines.len() as u32,
+            board.mines_per_cell,
+        ),
+        None => (options_grid, options.mines, options.mines_per_cell),
+    };
+    //co-op field comes without the mines, so server tells how many there are
+    let total_mine_count = coop
+        .as_ref()
+        .map(|coop| coop.mine_count)
+        .unwrap_or(total_mine_count);
+    let (width, height, shape) = (empty.width(), empty.height(), empty.shape());
+    let hot_seat = replay
+        .as_ref()
+        .map(|replay| replay.hot_seat)
+        .unwrap_or(options.hot_seat);
+    let tile_size = options.tile_size;
+
+    let mine_display_digits = segmented_display_digits(total_mine_count as u64);
+    //there is no limit on how long game can take, but on big fields it takes longer
+    //so timer gets roughly a second for every tile before it stops
+    let time_display_digits = segmented_display_digits((width * height) as u64);
+    //face is in the center, so the wider display decides how wide the window has to be
+    //to fit displays on both sides of the face with a bit of space between them
+    let widest_display =
+        mine_display_digits.max(time_display_digits) as i32 * SEGMENTED_DIGIT_WIDTH;
+    let displays_width = (DISPLAY_MARGIN + widest_display + 10 + 25) * 2;
+    let field_size = field_pixels(shape, width, height, tile_size);
+    let window_size = Vector2::new(
+        field_size.x.max(MIN_WINDOW_WIDTH).max(displays_width),
+        field_size.y + CONTROLS_PANEL_SIZE,
+    );
+    let (mut world, sdl, video_subsystem, ttf_context, mut canvas, _game) = setup::setup(
+        "Rust Minesweeper by MetalPizzaCat".to_owned(),
+        Some(Vector2::new(window_size.x as u32, window_size.y as u32)),
     )?;
-    let mut now = SystemTime::now();
-    'game: loop {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => {
-                    break 'game;
+    //window can't be smaller than the panel, and at least a few rows of the field should be visible
+    let min_window_size = Vector2::new(
+        MIN_WINDOW_WIDTH.max(displays_width),
+        height.min(10) as i32 * MIN_TILE_SIZE + CONTROLS_PANEL_SIZE,
+    );
+    let window_size = setup_window(&mut canvas, &video_subsystem, window_size, min_window_size)?;
+    let status = race
+        .as_ref()
+        .map(|race| race.status_text())
+        .or(coop.as_ref().map(|coop| coop.status_text()));
+    if let Some(status) = status {
+        canvas
+            .window_mut()
+            .set_title(&status)
+            .map_err(|e| e.to_string())?;
+    }
+    let mut layout = ScreenLayout::fit(window_size, width, height, shape);
+    let mut camera = Camera::default();
+    //gameplay systems send events through this channel, readers have to be registered before they are sent
+    world.insert(GameEvents::new());
+    //finished games are saved as replays once the game sends the result
+    let mut result_reader = world.write_resource::<GameEvents>().register_reader();
+    let mut dispatcher = make_dispatcher(&mut world);
+    ui::register_ui_components(&mut world);
+    let mut event_pump = sdl.event_pump().unwrap();
+
+    let texture_creator = canvas.texture_creator();
+    let mut atlas = TextureAtlas::new(&texture_creator);
+    let themes = assets::load_themes(&assets::assets_dir().join(assets::THEMES_DIR));
+    let theme_names: Vec<String> = themes.iter().map(|theme| theme.name.clone()).collect();
+    //if theme from the settings is gone, default theme is used instead
+    let theme = themes
+        .iter()
+        .find(|theme| theme.name == settings.theme)
+        .unwrap_or(&themes[0]);
+    //fallback art is only used for textures that are missing, so failing to make it is not fatal
+    let fallback_theme = match fallback_art::generate_fallback_theme(&mut atlas) {
+        Ok(fallback) => Some(fallback),
+        Err(e) => {
+            println!("Failed to draw fallback art: {}", e);
+            None
+        }
+    };
+    //all asset errors are collected so that player can fix everything at once
+    let mut asset_errors = Vec::new();
+    if let Err(errors) = assets::load_textures(&mut atlas, theme, fallback_theme.as_ref()) {
+        asset_errors.extend(errors);
+    }
+    //register components necessary for ECS world to function
+    world.register::<FaceButton>();
+    world.insert(ui::MouseData::default());
+    let font = match load_font(&ttf_context, &mut asset_errors) {
+        Some(font) if asset_errors.is_empty() => font,
+        _ => return Err(assets::show_errors(canvas.window(), &asset_errors)),
+    };
+    world.register::<SettingsButton>();
+    let mut input_mapper = InputMapper::new(settings.controls.clone());
+    //everyone in a race has to play exactly the same field, so mines are never moved
+    let in_race = race.is_some();
+    let rules_from_settings = |settings: &Settings| GameRules {
+        safe_first_click: settings.safe_first_click && !in_race,
+        win_on_clear: in_race,
+        ..GameRules::from_settings(settings)
+    };
+    world.insert(rules_from_settings(&settings));
+    let mut cursor = KeyboardCursor::default();
+
+    //draws the field, whole field is a single entity made by it
+    let board_renderer = BoardRenderer::new(&mut world, camera.view(&layout));
+
+    //game variables are resources that are used by the gameplay systems
+    //measures time of the game, starts on the first reveal
+    world.insert(GameClock::default());
+    world.insert(GameState::Active);
+    world.insert(HotSeat::new(hot_seat));
+    world.insert(PlayerCursors::default());
+    world.insert(InputQueue::default());
+    //actions of the current game, saved as replay once the game ends
+    let mut recorded_actions: Vec<ReplayAction> = Vec::new();
+    //index of the next action of the replay that is being played
+    let mut next_replay_action: usize = 0;
+    //timeline of the replay, unlike the game clock it starts as soon as the game is created
+    let mut replay_clock = GameClock::default();
+    replay_clock.start();
+    //entities of the settings screen, empty if it is closed
+    let mut settings_screen: Vec<Entity> = Vec::new();
+    //state to return to once settings screen is closed
+    let mut state_before_settings = GameState::Active;
+    //panel that covers the field while the game is paused
+    let mut pause_cover: Option<Entity> = None;
+
+    let (panel, rule_set_label) = make_panel(
+        &mut world,
+        &layout,
+        mine_display_digits,
+        time_display_digits,
+        rule_set_name(&empty).to_owned(),
+        hot_seat,
+    );
+    let grid = match loaded_board {
+        //mines of the co-op field are only known to the server
+        _ if coop.is_some() => empty.clone(),
+        Some(board) => grid_from_layout(board),
+        None => generate_grid(empty.clone(), total_mine_count, mines_per_cell, &mut rng),
+    };
+    let mut board = Board::new(grid, total_mine_count, mines_per_cell);
+    if let Some(save) = &save {
+        restore_save(&mut board, save);
+    }
+    start_game(&mut world, board);
+    if let Some(save) = &save {
+        world
+            .write_resource::<GameClock>()
+            .start_from(Duration::from_millis(save.time_ms));
+    }
+    loop {
+        //state is only changed by the systems while they run, so it's safe to keep a copy until then
+        let mut current_state = *world.read_resource::<GameState>();
+        let (inputs, changes) = poll_events(
+            &mut event_pump,
+            &mut input_mapper,
+            &world,
+            &camera.view(&layout),
+            &mut cursor,
+            current_state,
+        );
+        if changes.quit {
+            //scores are not saved, so games of two players can't be continued,
+            //and other players of a network game won't wait for it to be continued
+            if replay.is_none() && !hot_seat && race.is_none() && coop.is_none() {
+                autosave(&world, current_state);
+            }
+            break;
+        }
+        if let Some(size) = changes.resized {
+            layout = ScreenLayout::fit(size, width, height, shape);
+            camera.clamp(&layout);
+            resize_panel(&mut world, &layout, panel, rule_set_label);
+            //covers are simply made again with the new size
+            let (area_position, area_size) = layout.field_area();
+            if let Some(cover) = pause_cover.take() {
+                world.delete_entity(cover).map_err(|e| e.to_string())?;
+                pause_cover = Some(make_pause_cover(&mut world, area_position, area_size));
+            }
+            if !settings_screen.is_empty() {
+                for entity in settings_screen.drain(..) {
+                    world.delete_entity(entity).map_err(|e| e.to_string())?;
+                }
+                settings_screen =
+                    make_settings_screen(&mut world, area_position, area_size, &settings);
+            }
+        }
+        let FrameActions {
+            game: mut actions,
+            camera: camera_actions,
+            settings: setting_changes,
+        } = map_inputs(
+            &world,
+            inputs,
+            current_state,
+            &camera.view(&layout),
+            &cursor,
+        );
+        for (kind, forward) in setting_changes {
+            settings.change(kind, forward, &theme_names);
+            world.insert(rules_from_settings(&settings));
+            if kind == SettingKind::Theme {
+                apply_theme(
+                    &mut atlas,
+                    &themes,
+                    &settings.theme,
+                    fallback_theme.as_ref(),
+                    canvas.window(),
+                )?;
+            }
+            if let Err(e) = settings.save() {
+                println!("Failed to save settings: {}", e);
+            }
+            update_settings_screen(&mut world, &settings_screen, &settings);
+        }
+        if race.is_some() {
+            //field of the race can't be changed and hints would give an unfair advantage
+            actions.retain(|(action, _)| !matches!(action, GameAction::Restart | GameAction::Hint));
+        }
+        if let Some(coop) = &mut coop {
+            //field is changed by the server, so actions on it are sent there instead of the input queue.
+            //others keep playing, so the game can't be paused or restarted
+            actions.retain(|(action, point)| {
+                let sent = match action {
+                    GameAction::Reveal => CoopAction::Reveal,
+                    GameAction::Flag => CoopAction::Flag,
+                    GameAction::Chord => CoopAction::Chord,
+                    GameAction::Restart | GameAction::Hint | GameAction::Pause => return false,
+                    _ => return true,
+                };
+                coop.send_action(sent, *point);
+                false
+            });
+        }
+        if let Some(replay) = &replay {
+            //player can only watch the replay, so their actions are replaced with recorded ones
+            //camera can still be moved while watching
+            actions.retain(|(action, _)| {
+                matches!(
+                    action,
+                    GameAction::Restart | GameAction::Pause | GameAction::Settings
+                )
+            });
+            if !matches!(current_state, GameState::Paused | GameState::Setup) {
+                actions.extend(due_replay_actions(
+                    replay,
+                    &mut next_replay_action,
+                    replay_clock.elapsed().as_millis() as u64,
+                    &world.read_resource::<Board>().grid,
+                ));
+            }
+        }
+        if current_state != GameState::Paused {
+            for (action, anchor) in camera_actions {
+                match action {
+                    GameAction::Pan(delta) => camera.pan(delta, &layout),
+                    GameAction::ZoomIn => camera.zoom_by(ZOOM_STEP, anchor, &layout),
+                    GameAction::ZoomOut => camera.zoom_by(1.0 / ZOOM_STEP, anchor, &layout),
+                    _ => {}
                 }
-                Event::MouseMotion { x, y, .. } => {
-                    *world.write_resource::<ui::MouseData>() = ui::MouseData { x, y };
+            }
+        }
+        //actions on the field are done by the gameplay systems
+        let mut cell_inputs: Vec<CellInput> = Vec::new();
+        for (action, point) in actions {
+            if let Some(kind) = replay_action_kind(action) {
+                if !matches!(current_state, GameState::Paused | GameState::Setup)
+                    && replay.is_none()
+                {
+                    recorded_actions.push(ReplayAction {
+                        time_ms: replay_clock.elapsed().as_millis() as u64,
+                        kind,
+                        position: [point.row as i32, point.col as i32],
+                    });
                 }
-                Event::MouseButtonDown {
-                    x, y, mouse_btn, ..
-                } => {
-                    if let Some(face) = ui::get_overlapping_component_with_type::<FaceButton>(
-                        Vector2::new(x, y),
-                        world.system_data(),
-                    ) {
-                        //restart the game
-                        grid = generate_game(
-                            &mut world,
-                            &mut buttons,
-                            total_mine_count,
-                            area_size,
-                            controls_panel_size as i32,
-                            &mine_display
-                        )?;
-                        time = 0;
-                        continue;
+            }
+            match action {
+                GameAction::Settings => {
+                    if current_state == GameState::Setup {
+                        for entity in settings_screen.drain(..) {
+                            world.delete_entity(entity).map_err(|e| e.to_string())?;
+                        }
+                        current_state = state_before_settings;
+                        if current_state != GameState::Paused {
+                            replay_clock.resume();
+                        }
+                    } else {
+                        //settings screen works as a pause
+                        replay_clock.pause();
+                        state_before_settings = current_state;
+                        current_state = GameState::Setup;
+                        let (area_position, area_size) = layout.field_area();
+                        settings_screen =
+                            make_settings_screen(&mut world, area_position, area_size, &settings);
                     }
-                    //we have to offset y due to the fact that controls are on top
-                    let y = y - controls_panel_size as i32;
-                    match mouse_btn {
-                        sdl2::mouse::MouseButton::Left => {
-                            //x and y are swapped because i accidentally swapped them in memory
-                            reveal_block(
-                                Vector2::new(y / 50, x / 50),
-                                &mut grid,
-                                &mut world,
-                                &buttons,
-                            );
-                            if grid[(y / 50) as usize][(x / 50) as usize].bomb {
-                                end_game(false, &mut world, &face, &buttons, &grid, area_size);
-                            }
+                }
+                GameAction::Restart => {
+                    let grid = match &replay {
+                        Some(replay) => grid_from_layout(&replay.board),
+                        None => {
+                            generate_grid(empty.clone(), total_mine_count, mines_per_cell, &mut rng)
                         }
-                        sdl2::mouse::MouseButton::Right => {
-                            //this is where we have to put flag on top of the thing
-                            if flag_block(
-                                Vector2::new(y / 50, x / 50),
-                                &mut grid,
-                                &mut world,
-                                &buttons,
-                                &mut flag_count,
-                                total_mine_count as i32,
-                            ) {
-                                update_segmented_display(
-                                    &mut world,
-                                    &mine_display,
-                                    (total_mine_count as i32 - flag_count) as u32,
-                                );
-                            }
-                            if check_mines(&mut grid, area_size) {
-                                end_game(true, &mut world, &face, &buttons, &grid, area_size);
-                            }
+                    };
+                    //tiles don't need to be made again, board renderer will draw the new grid
+                    start_game(
+                        &mut world,
+                        Board::new(grid, total_mine_count, mines_per_cell),
+                    );
+                    //actions made before the restart were meant for the old field
+                    cell_inputs.clear();
+                    if let Some(cover) = pause_cover.take() {
+                        world.delete_entity(cover).map_err(|e| e.to_string())?;
+                    }
+                    current_state = GameState::Active;
+                    recorded_actions.clear();
+                    next_replay_action = 0;
+                    replay_clock.reset();
+                    replay_clock.start();
+                }
+                GameAction::Pause => match current_state {
+                    GameState::Paused => {
+                        //time spent on pause should not count towards the game
+                        replay_clock.resume();
+                        if let Some(cover) = pause_cover.take() {
+                            world.delete_entity(cover).map_err(|e| e.to_string())?;
                         }
-                        _ => {}
+                        current_state = GameState::Active;
+                    }
+                    GameState::Active => {
+                        replay_clock.pause();
+                        let (area_position, area_size) = layout.field_area();
+                        pause_cover = Some(make_pause_cover(&mut world, area_position, area_size));
+                        current_state = GameState::Paused;
                     }
+                    //there is nothing to pause if game has ended
+                    _ => {}
+                },
+                _ if matches!(current_state, GameState::Paused) => {}
+                GameAction::MoveUp
+                | GameAction::MoveDown
+                | GameAction::MoveLeft
+                | GameAction::MoveRight => {
+                    let (columns, rows) = match action {
+                        GameAction::MoveUp => (0, -1),
+                        GameAction::MoveDown => (0, 1),
+                        GameAction::MoveLeft => (-1, 0),
+                        _ => (1, 0),
+                    };
+                    cursor.move_by(columns, rows, width, height);
+                    camera.show_tile(cursor.position, &layout);
+                    //cursor moves mouse data so that the selected tile gets highlighted
+                    //by the button system the same way as when mouse hovers over it
+                    let center = cursor.screen_position(&camera.view(&layout));
+                    *world.write_resource::<ui::MouseData>() = ui::MouseData {
+                        x: center.x,
+                        y: center.y,
+                    };
+                }
+                //camera actions were already handled
+                GameAction::Pan(_) | GameAction::ZoomIn | GameAction::ZoomOut => {}
+                GameAction::Reveal | GameAction::Flag | GameAction::Chord | GameAction::Hint => {
+                    cell_inputs.push(CellInput {
+                        action,
+                        cell: point,
+                    });
+                }
+            }
+        }
+        if let Some(coop) = &mut coop {
+            update_coop(coop, &world, cursor.position, canvas.window_mut())?;
+            //game is ended by the server, settings screen will return to the ended game
+            if coop.result.is_some() {
+                if current_state == GameState::Setup {
+                    state_before_settings = GameState::Ended;
+                } else {
+                    current_state = GameState::Ended;
                 }
-                _ => {}
             }
         }
+        world.write_resource::<InputQueue>().inputs = cell_inputs;
+        *world.write_resource::<GameState>() = current_state;
         dispatcher.dispatch(&world);
-        render_game(&world, &mut canvas, &texture_manager, &mut game, &font)?;
+        let events: Vec<GameEvent> = world
+            .read_resource::<GameEvents>()
+            .read(&mut result_reader)
+            .cloned()
+            .collect();
+        let game_ended = events
+            .iter()
+            .any(|event| matches!(event, GameEvent::GameWon | GameEvent::GameLost));
+        if let Some(race) = &mut race {
+            update_race(race, &world, &events, canvas.window_mut())?;
+        }
+        if game_ended && hot_seat {
+            let players = world.read_resource::<HotSeat>();
+            match players.winner() {
+                Some(winner) => println!(
+                    "Player {} wins with {} points",
+                    winner + 1,
+                    players.scores[winner]
+                ),
+                None => println!("Draw, both players have {} points", players.scores[0]),
+            }
+        }
+        //co-op field has no mines in it, so there is nothing to replay
+        if game_ended && replay.is_none() && coop.is_none() {
+            save_replay(&world, &recorded_actions, hot_seat);
+        }
+        let paused = *world.read_resource::<GameState>() == GameState::Paused;
+        board_renderer.sync(&mut world, &camera.view(&layout), paused)?;
+        render_frame(&world, &mut canvas, &atlas, Some(&font))?;
         //lock frames to run at 30 fps
         //this is minesweeper, why would you want more?
         ::std::thread::sleep(std::time::Duration::new(0, 1_000_000_000u32 / 30));
-        if let Ok(dur) = now.elapsed() {
-            if dur.as_secs_f32() >= 1.0 {
-                time += 1;
-                now = SystemTime::now();
-            }
-            update_segmented_display(&mut world, &time_display, time as u32);
-        }
     }
     Ok(())
 }
@@ -1,29 +1,69 @@
-/**This file contains functions for interacting with ui, 
+/**This file contains functions for interacting with ui,
  * that do not have direct effect on gameplay
  */
-
+use crate::settings::{SettingKind, Settings};
 use game_oxide_framework::components::*;
-use specs::{Entity, Builder, World, WorldExt};
+use game_oxide_framework::{layers, ui};
 use nalgebra::Vector2;
-use game_oxide_framework::layers;
-
-///Updates numbers in the segmented display
-pub fn update_segmented_display(world: &mut World, display: &Vec<Entity>, new_value: u32) {
-    let mut values: [u32; 3] = [0; 3];
-    values[2] = new_value % 10;
-    values[1] = (new_value % 100 - values[2]) / 10;
-    values[0] = (new_value - values[2] - values[1]) / 100;
-
-    for i in 0..3 {
-        if let Some(sprite) = world.write_component::<Sprite>().get_mut(display[i]) {
-            sprite.name = values[i].to_string();
+use specs::{Builder, Component, Entity, VecStorage, World, WorldExt};
+
+///Button on the settings screen that changes the setting
+#[derive(Component, Clone, Debug)]
+#[storage(VecStorage)]
+pub struct SettingsButton {
+    pub kind: SettingKind,
+}
+
+///Width of a single digit of the segmented display
+pub const SEGMENTED_DIGIT_WIDTH: i32 = 44;
+
+///Returns how many digits display needs to show every value up to `max_value`,
+/// displays always have at least 3 digits like in the original game
+pub fn segmented_display_digits(max_value: u64) -> usize {
+    max_value.to_string().len().max(3)
+}
+
+///Updates numbers in the segmented display, values that do not fit are clamped.
+/// Negative values are shown with minus sign in place of the first digit
+pub fn update_segmented_display(world: &mut World, display: &[Entity], new_value: i64) {
+    let values = segmented_display_textures(display.len(), new_value);
+    for (entity, value) in display.iter().zip(values) {
+        if let Some(sprite) = world.write_component::<Sprite>().get_mut(*entity) {
+            sprite.name = value;
+        }
+    }
+}
+
+///Names of the textures that display with given amount of digits uses to show the value
+pub fn segmented_display_textures(digits: usize, new_value: i64) -> Vec<String> {
+    let digits = digits as u32;
+    let max = 10i64.pow(digits) - 1;
+    let min = -(10i64.pow(digits.saturating_sub(1)) - 1);
+    let value = new_value.clamp(min, max);
+
+    //digits are collected from the last one to the first one
+    let mut values: Vec<String> = Vec::new();
+    let mut rest = value.abs();
+    for _ in 0..digits {
+        values.push((rest % 10).to_string());
+        rest /= 10;
+    }
+    if value < 0 {
+        if let Some(first) = values.last_mut() {
+            *first = "minus".to_owned();
         }
     }
+    values.reverse();
+    values
 }
 
-pub fn make_segmented_display(world: &mut World, position: Vector2<i32>) -> Vec<Entity> {
+pub fn make_segmented_display(
+    world: &mut World,
+    position: Vector2<i32>,
+    digits: usize,
+) -> Vec<Entity> {
     let mut res: Vec<Entity> = Vec::new();
-    for i in 0..3 {
+    for i in 0..digits as i32 {
         res.push(
             world
                 .create_entity()
@@ -34,7 +74,7 @@ pub fn make_segmented_display(world: &mut World, position: Vector2<i32>) -> Vec<
                     visible: true,
                 })
                 .with(Position {
-                    x: position.x + 44 * i,
+                    x: position.x + SEGMENTED_DIGIT_WIDTH * i,
                     y: position.y,
                 })
                 .with(Renderable::new(true, layers::RenderLayers::Gameplay as u32))
@@ -43,3 +83,194 @@ pub fn make_segmented_display(world: &mut World, position: Vector2<i32>) -> Vec<
     }
     res
 }
+
+///Moves display so that its first digit is at the given position
+pub fn move_segmented_display(world: &mut World, display: &[Entity], position: Vector2<i32>) {
+    for (i, entity) in display.iter().enumerate() {
+        if let Some(digit) = world.write_component::<Position>().get_mut(*entity) {
+            digit.x = position.x + SEGMENTED_DIGIT_WIDTH * i as i32;
+            digit.y = position.y;
+        }
+    }
+}
+
+///Shows or hides all digits of the display
+pub fn set_segmented_display_visible(world: &mut World, display: &Vec<Entity>, visible: bool) {
+    for entity in display {
+        if let Some(digit) = world.write_component::<Sprite>().get_mut(*entity) {
+            digit.visible = visible;
+        }
+    }
+}
+
+///Width and height of the label that shows which tiles are neighbors
+pub const RULE_SET_LABEL_SIZE: Vector2<i32> = Vector2::new(100, 20);
+
+///Creates label on the panel that shows the name of the neighborhood used by the field
+pub fn make_rule_set_label(world: &mut World, position: Vector2<i32>, name: String) -> Entity {
+    world
+        .create_entity()
+        .with(Position {
+            x: position.x,
+            y: position.y,
+        })
+        .with(Rectangle {
+            width: RULE_SET_LABEL_SIZE.x,
+            height: RULE_SET_LABEL_SIZE.y,
+        })
+        .with(Colored {
+            color: sdl2::pixels::Color::RGB(160, 160, 160),
+        })
+        .with(Text {
+            text: name,
+            color: sdl2::pixels::Color::BLACK,
+            visible: true,
+            offset: Vector2::new(5, 0),
+        })
+        .with(Renderable::new(true, layers::RenderLayers::Gameplay as u32))
+        .build()
+}
+
+///Creates panel that covers the field while the game is paused
+pub fn make_pause_cover(world: &mut World, position: Vector2<i32>, size: Vector2<i32>) -> Entity {
+    world
+        .create_entity()
+        .with(Position {
+            x: position.x,
+            y: position.y,
+        })
+        .with(Rectangle {
+            width: size.x,
+            height: size.y,
+        })
+        .with(Colored {
+            color: sdl2::pixels::Color::RGB(192, 192, 192),
+        })
+        .with(Text {
+            text: "Paused".to_owned(),
+            color: sdl2::pixels::Color::BLACK,
+            visible: true,
+            offset: Vector2::new(size.x / 2 - 75, size.y / 2 - 25),
+        })
+        .with(Renderable::new(true, layers::RenderLayers::Gameplay as u32))
+        .build()
+}
+
+///Creates settings screen on top of everything else, returns all entities that it is made of
+pub fn make_settings_screen(
+    world: &mut World,
+    position: Vector2<i32>,
+    size: Vector2<i32>,
+    settings: &Settings,
+) -> Vec<Entity> {
+    let mut res: Vec<Entity> = Vec::new();
+    res.push(
+        world
+            .create_entity()
+            .with(Position {
+                x: position.x,
+                y: position.y,
+            })
+            .with(Rectangle {
+                width: size.x,
+                height: size.y,
+            })
+            .with(Colored {
+                color: sdl2::pixels::Color::RGB(192, 192, 192),
+            })
+            .with(Renderable::new(true, layers::RenderLayers::Gameplay as u32))
+            .build(),
+    );
+    for (i, kind) in SettingKind::ALL.iter().enumerate() {
+        let label = settings.label(*kind);
+        res.push(
+            ui::make_button_base(
+                world,
+                Vector2::new(position.x + 10, position.y + 10 + i as i32 * 55),
+                Vector2::new(size.x - 20, 50),
+                Some(ui::Button {
+                    hovered_over: false,
+                    hovered_over_texture_name: None,
+                    hovered_over_text: Some(label.clone()),
+                    hovered_over_color: Some(sdl2::pixels::Color::RGB(220, 220, 220)),
+                    normal_texture_name: None,
+                    normal_text: Some(label.clone()),
+                    normal_color: Some(sdl2::pixels::Color::RGB(160, 160, 160)),
+                }),
+                sdl2::pixels::Color::RGB(160, 160, 160),
+                layers::RenderLayers::Gameplay,
+            )
+            .with(Text {
+                text: label,
+                color: sdl2::pixels::Color::BLACK,
+                visible: true,
+                offset: Vector2::new(10, 0),
+            })
+            .with(SettingsButton { kind: *kind })
+            .build(),
+        );
+    }
+    res
+}
+
+///Updates text on the settings screen buttons to match the settings
+pub fn update_settings_screen(world: &mut World, screen: &Vec<Entity>, settings: &Settings) {
+    for entity in screen {
+        let kind = match world.read_component::<SettingsButton>().get(*entity) {
+            Some(button) => button.kind,
+            None => continue,
+        };
+        let label = settings.label(kind);
+        if let Some(button) = world.write_component::<ui::Button>().get_mut(*entity) {
+            button.normal_text = Some(label.clone());
+            button.hovered_over_text = Some(label.clone());
+        }
+        if let Some(text) = world.write_component::<Text>().get_mut(*entity) {
+            text.text = label;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn textures(digits: usize, value: i64) -> Vec<String> {
+        segmented_display_textures(digits, value)
+    }
+
+    #[test]
+    fn display_has_a_digit_for_every_digit_of_the_largest_value() {
+        assert_eq!(segmented_display_digits(0), 3);
+        assert_eq!(segmented_display_digits(999), 3);
+        assert_eq!(segmented_display_digits(1000), 4);
+        assert_eq!(segmented_display_digits(u64::MAX), 20);
+    }
+
+    #[test]
+    fn negative_values_start_with_minus() {
+        assert_eq!(textures(3, -5), ["minus", "0", "5"]);
+        assert_eq!(textures(3, 7), ["0", "0", "7"]);
+    }
+
+    #[test]
+    fn values_that_do_not_fit_are_clamped() {
+        assert_eq!(textures(3, 999), ["9", "9", "9"]);
+        assert_eq!(textures(3, 1000), ["9", "9", "9"]);
+        assert_eq!(textures(3, -99), ["minus", "9", "9"]);
+        assert_eq!(textures(3, -100), ["minus", "9", "9"]);
+    }
+
+    #[test]
+    fn single_digit_display_has_no_room_for_minus() {
+        assert_eq!(textures(1, -5), ["0"]);
+        assert_eq!(textures(1, 12), ["9"]);
+    }
+
+    #[test]
+    fn wide_display_shows_every_digit() {
+        assert_eq!(textures(5, 12345), ["1", "2", "3", "4", "5"]);
+        assert_eq!(textures(5, -42), ["minus", "0", "0", "4", "2"]);
+        assert_eq!(textures(5, -123456), ["minus", "9", "9", "9", "9"]);
+    }
+}
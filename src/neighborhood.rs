@@ -0,0 +1,359 @@
+/**This file contains neighborhoods: rule sets that decide which tiles count as neighbors of a tile.
+ * There are a few built in ones and any other can be loaded from a json file, for example:
+ * { "name": "Knight", "offsets": [[-2, -1], [-2, 1], [-1, -2], [-1, 2], [1, -2], [1, 2], [2, -1], [2, 1]] }
+ * Offsets are [row, column] pairs like positions in other files
+ */
+use crate::grid::CellShape;
+use crate::options::MAX_TILE_VALUE;
+use serde::{Deserialize, Serialize};
+
+///Names of the built in neighborhoods
+pub const PRESETS: [&str; 4] = ["standard", "knight", "cross", "5x5"];
+
+///Neighborhood as it's written in files
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct NeighborhoodFile {
+    name: String,
+    offsets: Vec<[i32; 2]>,
+    ///Offsets that flood reveal goes to, all of the neighbors if there are none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    flood_offsets: Option<Vec<[i32; 2]>>,
+}
+
+///Set of offsets from a tile to its neighbors, checked to be usable when it's created
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "NeighborhoodFile", into = "NeighborhoodFile")]
+pub struct Neighborhood {
+    name: String,
+    ///Column and row offsets of the neighbors
+    offsets: Vec<(i32, i32)>,
+    flood_offsets: Option<Vec<(i32, i32)>>,
+}
+
+///All offsets in the square around the tile that are at most `radius` tiles away
+fn square_offsets(radius: i32) -> Vec<(i32, i32)> {
+    let mut offsets: Vec<(i32, i32)> = Vec::new();
+    for rows in -radius..=radius {
+        for cols in -radius..=radius {
+            if rows != 0 || cols != 0 {
+                offsets.push((cols, rows));
+            }
+        }
+    }
+    offsets
+}
+
+impl Neighborhood {
+    ///Creates neighborhood and checks that offsets make sense
+    pub fn new(
+        name: String,
+        offsets: Vec<(i32, i32)>,
+        flood_offsets: Option<Vec<(i32, i32)>>,
+    ) -> Result<Neighborhood, String> {
+        if offsets.is_empty() {
+            return Err(format!("Neighborhood \"{}\" has no offsets", name));
+        }
+        for list in std::iter::once(&offsets).chain(flood_offsets.iter()) {
+            for (i, offset) in list.iter().enumerate() {
+                if *offset == (0, 0) {
+                    return Err(format!(
+                        "Neighborhood \"{}\" has tile itself as a neighbor",
+                        name
+                    ));
+                }
+                if list[..i].contains(offset) {
+                    return Err(format!(
+                        "Neighborhood \"{}\" has offset [{}, {}] more than once",
+                        name, offset.1, offset.0
+                    ));
+                }
+            }
+        }
+        //every number needs a texture, so tile can't have more neighbors than there are numbers
+        if offsets.len() as i32 > MAX_TILE_VALUE {
+            return Err(format!(
+                "Neighborhood \"{}\" has {} neighbors, at most {} are supported",
+                name,
+                offsets.len(),
+                MAX_TILE_VALUE
+            ));
+        }
+        Ok(Neighborhood {
+            name,
+            offsets,
+            flood_offsets,
+        })
+    }
+
+    ///Returns one of the built in neighborhoods
+    pub fn preset(name: &str) -> Option<Neighborhood> {
+        let sides = vec![(0, -1), (-1, 0), (0, 1), (1, 0)];
+        let (offsets, flood_offsets) = match name {
+            //same as the original game, flood only goes through the sides
+            "standard" => (square_offsets(1), Some(sides)),
+            "knight" => (
+                vec![
+                    (-1, -2),
+                    (1, -2),
+                    (-2, -1),
+                    (2, -1),
+                    (-2, 1),
+                    (2, 1),
+                    (-1, 2),
+                    (1, 2),
+                ],
+                None,
+            ),
+            "cross" => (sides, None),
+            "5x5" => (square_offsets(2), None),
+            _ => return None,
+        };
+        Neighborhood::new(name.to_owned(), offsets, flood_offsets).ok()
+    }
+
+    ///Loads neighborhood from the json file
+    pub fn load(path: &str) -> Result<Neighborhood, String> {
+        let data =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path, e))
+    }
+
+    ///Built in neighborhood if there is one with this name, otherwise it's loaded from the file
+    pub fn from_name_or_file(value: &str) -> Result<Neighborhood, String> {
+        match Neighborhood::preset(value) {
+            Some(neighborhood) => Ok(neighborhood),
+            None => Neighborhood::load(value)
+                .map_err(|e| format!("{}\nBuilt in neighborhoods are: {}", e, PRESETS.join(", "))),
+        }
+    }
+
+    ///Checks that the neighborhood can be used on the field with the given tiles
+    pub fn check_fits(&self, shape: CellShape, mines_per_cell: u32) -> Result<(), String> {
+        //hex rows are shifted, so offsets would mean different tiles in odd and even rows
+        if shape != CellShape::Square {
+            return Err(format!(
+                "Neighborhood \"{}\" can only be used with square tiles",
+                self.name
+            ));
+        }
+        let most_mines = (self.offsets.len() as u32 * mines_per_cell) as i32;
+        if most_mines > MAX_TILE_VALUE {
+            return Err(format!(
+                "Tiles with neighborhood \"{}\" can have {} mines around them, at most {} are supported",
+                self.name, most_mines, MAX_TILE_VALUE
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn offsets(&self) -> &[(i32, i32)] {
+        &self.offsets
+    }
+
+    pub fn flood_offsets(&self) -> &[(i32, i32)] {
+        self.flood_offsets.as_ref().unwrap_or(&self.offsets)
+    }
+}
+
+impl TryFrom<NeighborhoodFile> for Neighborhood {
+    type Error = String;
+
+    fn try_from(file: NeighborhoodFile) -> Result<Self, Self::Error> {
+        let to_offsets = |list: Vec<[i32; 2]>| -> Vec<(i32, i32)> {
+            list.into_iter().map(|[rows, cols]| (cols, rows)).collect()
+        };
+        Neighborhood::new(
+            file.name,
+            to_offsets(file.offsets),
+            file.flood_offsets.map(to_offsets),
+        )
+    }
+}
+
+impl From<Neighborhood> for NeighborhoodFile {
+    fn from(neighborhood: Neighborhood) -> Self {
+        let to_pairs = |list: Vec<(i32, i32)>| -> Vec<[i32; 2]> {
+            list.into_iter().map(|(cols, rows)| [rows, cols]).collect()
+        };
+        NeighborhoodFile {
+            name: neighborhood.name,
+            offsets: to_pairs(neighborhood.offsets),
+            flood_offsets: neighborhood.flood_offsets.map(to_pairs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameplay::{grid_from_layout, reveal_block};
+    use crate::grid::{CellCoord, Grid, Topology};
+    use crate::save::BoardLayout;
+    use crate::Field;
+
+    ///Writes rules file to the temporary directory and loads it like `--neighborhood` does
+    fn load_rules(name: &str, data: &str) -> Result<Neighborhood, String> {
+        let directory = std::env::temp_dir().join("minesweeper-ecs");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(format!("{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let neighborhood = Neighborhood::from_name_or_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        neighborhood
+    }
+
+    fn knight_grid(mines: Vec<[usize; 2]>) -> Grid<Field> {
+        grid_from_layout(&BoardLayout {
+            width: 4,
+            height: 4,
+            shape: CellShape::Square,
+            topology: Topology::Bounded,
+            neighborhood: Neighborhood::preset("knight"),
+            mines_per_cell: 1,
+            mines,
+        })
+    }
+
+    #[test]
+    fn every_preset_can_be_used() {
+        let sizes = [
+            ("standard", 8, 4),
+            ("knight", 8, 8),
+            ("cross", 4, 4),
+            ("5x5", 24, 24),
+        ];
+        for (name, neighbors, flood) in sizes {
+            let neighborhood = Neighborhood::from_name_or_file(name).unwrap();
+            assert_eq!(neighborhood.name(), name);
+            assert_eq!(neighborhood.offsets().len(), neighbors, "{}", name);
+            assert_eq!(neighborhood.flood_offsets().len(), flood, "{}", name);
+        }
+        assert_eq!(sizes.map(|(name, _, _)| name), PRESETS);
+        assert!(Neighborhood::preset("queen").is_none());
+    }
+
+    #[test]
+    fn rules_file_is_loaded_as_row_and_column_pairs() {
+        let neighborhood = load_rules(
+            "diagonal",
+            r#"{"name": "Diagonal", "offsets": [[-1, 2], [1, -2]], "flood_offsets": [[-1, 2]]}"#,
+        )
+        .unwrap();
+        assert_eq!(neighborhood.name(), "Diagonal");
+        assert_eq!(neighborhood.offsets(), &[(2, -1), (-2, 1)]);
+        assert_eq!(neighborhood.flood_offsets(), &[(2, -1)]);
+    }
+
+    #[test]
+    fn malformed_rules_file_is_explained() {
+        let error = load_rules("broken", r#"{"name": "Broken", "offsets": [[1, 2]"#).unwrap_err();
+        assert!(error.starts_with("Failed to parse"), "{}", error);
+        assert!(
+            error.ends_with("Built in neighborhoods are: standard, knight, cross, 5x5"),
+            "{}",
+            error
+        );
+        let error = Neighborhood::from_name_or_file("no-such-rules.json").unwrap_err();
+        assert!(
+            error.starts_with("Failed to read no-such-rules.json"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn invalid_offsets_are_refused() {
+        let file = |offsets: Vec<[i32; 2]>| NeighborhoodFile {
+            name: "Bad".to_owned(),
+            offsets,
+            flood_offsets: None,
+        };
+        assert_eq!(
+            Neighborhood::try_from(file(vec![])),
+            Err("Neighborhood \"Bad\" has no offsets".to_owned())
+        );
+        assert_eq!(
+            Neighborhood::try_from(file(vec![[1, 0], [0, 0]])),
+            Err("Neighborhood \"Bad\" has tile itself as a neighbor".to_owned())
+        );
+        assert_eq!(
+            Neighborhood::try_from(file(vec![[1, 2], [1, 2]])),
+            Err("Neighborhood \"Bad\" has offset [1, 2] more than once".to_owned())
+        );
+        assert_eq!(
+            Neighborhood::try_from(file((1..=25).map(|row| [row, 0]).collect())),
+            Err("Neighborhood \"Bad\" has 25 neighbors, at most 24 are supported".to_owned())
+        );
+        //validation errors are shown for files too
+        let error = load_rules("itself", r#"{"name": "Itself", "offsets": [[0, 0]]}"#).unwrap_err();
+        assert!(
+            error.contains("Neighborhood \"Itself\" has tile itself as a neighbor"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn neighborhood_has_to_fit_the_field() {
+        let knight = Neighborhood::preset("knight").unwrap();
+        assert_eq!(knight.check_fits(CellShape::Square, 3), Ok(()));
+        assert_eq!(
+            knight.check_fits(CellShape::Hex, 1),
+            Err("Neighborhood \"knight\" can only be used with square tiles".to_owned())
+        );
+        let big = Neighborhood::preset("5x5").unwrap();
+        assert_eq!(big.check_fits(CellShape::Square, 1), Ok(()));
+        assert_eq!(
+            big.check_fits(CellShape::Square, 2),
+            Err("Tiles with neighborhood \"5x5\" can have 48 mines around them, at most 24 are supported".to_owned())
+        );
+    }
+
+    #[test]
+    fn knight_numbers_count_mines_a_knight_move_away() {
+        let grid = knight_grid(vec![[0, 0]]);
+        let mine = CellCoord::new(0, 0);
+        for cell in grid.cells().filter(|cell| *cell != mine) {
+            let expected = grid.reverse_neighbors(mine).contains(&cell) as i32;
+            assert_eq!(grid[cell].value, expected, "{:?}", cell);
+        }
+        assert_eq!(grid[CellCoord::new(1, 2)].value, 1);
+        assert_eq!(grid[CellCoord::new(2, 1)].value, 1);
+        //tile right next to the mine can't reach it with a knight move
+        assert_eq!(grid[CellCoord::new(1, 1)].value, 0);
+    }
+
+    #[test]
+    fn knight_reveal_floods_through_knight_moves() {
+        let mut grid = knight_grid(vec![[0, 0]]);
+        let mut revealed: Vec<CellCoord> = Vec::new();
+        reveal_block(CellCoord::new(3, 3), &mut grid, &mut revealed);
+        //both tiles a knight move away are numbers, so flood stops there
+        //and the tiles next to the clicked one stay hidden
+        revealed.sort_by_key(|cell| (cell.row, cell.col));
+        assert_eq!(
+            revealed,
+            vec![
+                CellCoord::new(2, 1),
+                CellCoord::new(1, 2),
+                CellCoord::new(3, 3)
+            ]
+        );
+
+        let mut grid = knight_grid(vec![[3, 3]]);
+        let mut revealed: Vec<CellCoord> = Vec::new();
+        reveal_block(CellCoord::new(0, 0), &mut grid, &mut revealed);
+        for cell in &revealed {
+            if !grid[*cell].border {
+                for neighbor in grid.reverse_neighbors(*cell) {
+                    assert!(grid[neighbor].revealed || grid[neighbor].has_bomb());
+                }
+            }
+        }
+        assert!(!grid[CellCoord::new(3, 3)].revealed);
+    }
+}
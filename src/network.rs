@@ -0,0 +1,561 @@
+/**This file contains the protocol that multiplayer modes use to talk over TCP.
+ * Every message is a single line of json(json lines), so it can be read with a simple line reader
+ * and tried out by hand with any tool that can open a TCP connection.
+ * Every message has the version of the protocol and the type of the message, the rest depends on the type:
+ * {"version":2,"type":"hello","name":"Player","mode":"race"}
+ * {"version":2,"type":"progress","percent":40}
+ * Peer that gets a message of another version answers with an error message and closes the connection.
+ * Version 2 added the mode to hello and the co-op messages.
+ * Positions are [row, column] like in the save files
+ *
+ * Messages of every mode, client to server:
+ *  hello { name, mode }            first message after connecting, mode is "race" or "coop"
+ * Server to clients:
+ *  welcome { player }              index of the player, sent as the answer to hello
+ *  player_left { player }          player has disconnected
+ *  error { reason }                message could not be understood, connection is closed after it
+ *
+ * Race messages, client to server:
+ *  progress { percent }            part of the safe tiles player revealed, 0 to 100
+ *  finished { time_ms }            player cleared the board
+ *  lost                            player revealed a mine and is out of the race
+ * Race messages, server to clients:
+ *  start { board, players }        field that everyone plays and the names of the players
+ *  player_progress { player, percent }
+ *  player_finished { player, time_ms }
+ *  player_lost { player }
+ *  winner { player, time_ms }      first player that cleared the board, sent once
+ *
+ * Co-op messages, client to server:
+ *  act { action, position, revision }  reveal, flag or chord a tile, revision is the last one player has seen
+ *  cursor { position }             tile player is pointing at
+ * Co-op messages, server to clients:
+ *  coop_start { board, mine_count, players }  field without the mines, server keeps them to itself
+ *  cells { revision, cells }       tiles that have changed, every change gets the next revision
+ *  rejected { position, reason }   action was not made, usually because someone else changed the tile first
+ *  player_cursor { player, position }
+ *  game_over { won }               sent once, mines are revealed with cells right before it
+ */
+use crate::save::BoardLayout;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, TryRecvError};
+use std::time::Duration;
+
+///Version of the protocol, it changes every time messages change in a way old peers won't understand
+pub const PROTOCOL_VERSION: u32 = 2;
+///Port that is used if address does not have one
+pub const DEFAULT_PORT: u16 = 7878;
+///How long server waits for hello from a player that has just connected.
+/// Players are accepted one at a time, so a silent one would keep everyone else out
+pub const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+///What players are going to play, server only lets in players of its own mode
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    Race,
+    Coop,
+}
+
+impl GameMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Race => "race",
+            GameMode::Coop => "co-op",
+        }
+    }
+}
+
+///Actions that players of co-op games can make on the shared field
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoopAction {
+    Reveal,
+    Flag,
+    Chord,
+}
+
+///Tile of the shared field as players see it, mines are only sent once the tile is revealed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CellState {
+    pub position: [usize; 2],
+    pub revealed: bool,
+    #[serde(default)]
+    pub value: i32,
+    #[serde(default)]
+    pub bombs: u32,
+    #[serde(default)]
+    pub flags: u32,
+    #[serde(default)]
+    pub questioned: bool,
+    ///Player that placed the flags or the question mark
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    Hello {
+        name: String,
+        mode: GameMode,
+    },
+    Progress {
+        percent: u32,
+    },
+    Finished {
+        time_ms: u64,
+    },
+    Lost,
+    Act {
+        action: CoopAction,
+        position: [usize; 2],
+        revision: u64,
+    },
+    Cursor {
+        position: [usize; 2],
+    },
+    Welcome {
+        player: usize,
+    },
+    Start {
+        board: BoardLayout,
+        players: Vec<String>,
+    },
+    PlayerProgress {
+        player: usize,
+        percent: u32,
+    },
+    PlayerFinished {
+        player: usize,
+        time_ms: u64,
+    },
+    PlayerLost {
+        player: usize,
+    },
+    PlayerLeft {
+        player: usize,
+    },
+    Winner {
+        player: usize,
+        time_ms: u64,
+    },
+    CoopStart {
+        board: BoardLayout,
+        mine_count: u32,
+        players: Vec<String>,
+    },
+    Cells {
+        revision: u64,
+        cells: Vec<CellState>,
+    },
+    Rejected {
+        position: [usize; 2],
+        reason: String,
+    },
+    PlayerCursor {
+        player: usize,
+        position: [usize; 2],
+    },
+    GameOver {
+        won: bool,
+    },
+    Error {
+        reason: String,
+    },
+}
+
+///Message as it's sent over the connection
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    #[serde(flatten)]
+    message: Message,
+}
+
+///Only the version of the message, it's read first so that messages of other versions
+/// get a clear error instead of a parse error
+#[derive(Deserialize)]
+struct VersionOnly {
+    version: u32,
+}
+
+///Turns message into a line of json, line break included
+pub fn encode(message: &Message) -> Result<String, String> {
+    let envelope = Envelope {
+        version: PROTOCOL_VERSION,
+        message: message.clone(),
+    };
+    serde_json::to_string(&envelope)
+        .map(|line| line + "\n")
+        .map_err(|e| format!("Failed to encode message: {}", e))
+}
+
+///Reads message from a line of json
+pub fn decode(line: &str) -> Result<Message, String> {
+    let version: VersionOnly =
+        serde_json::from_str(line).map_err(|e| format!("Malformed message: {}", e))?;
+    if version.version != PROTOCOL_VERSION {
+        return Err(format!(
+            "Protocol version {} is not supported, expected {}",
+            version.version, PROTOCOL_VERSION
+        ));
+    }
+    serde_json::from_str::<Envelope>(line)
+        .map(|envelope| envelope.message)
+        .map_err(|e| format!("Malformed message: {}", e))
+}
+
+///Adds default port to the address if it does not have one
+pub fn with_default_port(address: &str) -> String {
+    if address.contains(':') {
+        address.to_owned()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+///Connection to the other side. Messages are read on a separate thread,
+/// so the game can check for new ones every frame without waiting
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Receiver<Result<Message, String>>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> Result<Connection, String> {
+        let reader = stream.try_clone().map_err(|e| e.to_string())?;
+        let (sender, incoming) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let message = match line {
+                    Ok(line) if line.trim().is_empty() => continue,
+                    Ok(line) => decode(&line),
+                    Err(e) => Err(format!("Connection failed: {}", e)),
+                };
+                let failed = message.is_err();
+                //game has dropped the connection, nobody is listening anymore
+                if sender.send(message).is_err() || failed {
+                    return;
+                }
+            }
+            let _ = sender.send(Err("Connection closed".to_owned()));
+        });
+        Ok(Connection { stream, incoming })
+    }
+
+    pub fn connect(address: &str) -> Result<Connection, String> {
+        let address = with_default_port(address);
+        let stream = TcpStream::connect(&address)
+            .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+        Connection::new(stream)
+    }
+
+    pub fn send(&mut self, message: &Message) -> Result<(), String> {
+        let line = encode(message)?;
+        self.stream
+            .write_all(line.as_bytes())
+            .map_err(|e| format!("Failed to send message: {}", e))
+    }
+
+    ///Sends the error to the other side and closes the connection
+    pub fn reject(&mut self, reason: &str) {
+        let _ = self.send(&Message::Error {
+            reason: reason.to_owned(),
+        });
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    ///Waits for the next message
+    pub fn receive(&self) -> Result<Message, String> {
+        self.incoming
+            .recv()
+            .unwrap_or_else(|_| Err("Connection closed".to_owned()))
+    }
+
+    ///Waits for the next message, but not longer than `timeout`
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<Message, String> {
+        match self.incoming.recv_timeout(timeout) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => Err(format!(
+                "Nothing was received in {} seconds",
+                timeout.as_secs_f32()
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err("Connection closed".to_owned()),
+        }
+    }
+
+    ///Returns the next message if there is one, does not wait
+    pub fn try_receive(&self) -> Option<Result<Message, String>> {
+        match self.incoming.try_recv() {
+            Ok(message) => Some(message),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("Connection closed".to_owned())),
+        }
+    }
+}
+
+///Starts listening for players on the port
+pub fn listen(port: u16) -> Result<TcpListener, String> {
+    TcpListener::bind(("0.0.0.0", port))
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))
+}
+
+///Waits until `players` players of the mode connect and say hello, every one of them gets their index.
+/// Players that don't say hello within `HELLO_TIMEOUT` are dropped.
+/// Returns the connections and the names of the players
+pub fn accept_players(
+    listener: &TcpListener,
+    players: usize,
+    mode: GameMode,
+) -> Result<(Vec<Option<Connection>>, Vec<String>), String> {
+    accept_players_within(listener, players, mode, HELLO_TIMEOUT)
+}
+
+fn accept_players_within(
+    listener: &TcpListener,
+    players: usize,
+    mode: GameMode,
+    hello_timeout: Duration,
+) -> Result<(Vec<Option<Connection>>, Vec<String>), String> {
+    let mut connections: Vec<Option<Connection>> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    while connections.len() < players {
+        let (stream, address) = listener.accept().map_err(|e| e.to_string())?;
+        let mut connection = Connection::new(stream)?;
+        let name = match connection.receive_timeout(hello_timeout) {
+            Ok(Message::Hello {
+                name,
+                mode: player_mode,
+            }) if player_mode == mode => name,
+            Ok(Message::Hello {
+                mode: player_mode, ..
+            }) => {
+                connection.reject(&format!(
+                    "Server hosts {} game, but player wants to play {}",
+                    mode.name(),
+                    player_mode.name()
+                ));
+                continue;
+            }
+            Ok(message) => {
+                connection.reject(&format!("Expected hello, got {:?}", message));
+                continue;
+            }
+            Err(e) => {
+                println!("Dropped player from {}: {}", address, e);
+                connection.reject(&e);
+                continue;
+            }
+        };
+        let player = connections.len();
+        //player who failed to get welcome will notice that when the game starts
+        let _ = connection.send(&Message::Welcome { player });
+        println!(
+            "{} joined from {} ({}/{})",
+            name,
+            address,
+            player + 1,
+            players
+        );
+        connections.push(Some(connection));
+        names.push(name);
+    }
+    Ok((connections, names))
+}
+
+///Sends the message to every player that is still connected
+pub fn broadcast(connections: &mut [Option<Connection>], message: &Message) {
+    for connection in connections.iter_mut().flatten() {
+        //players that failed to get it will be noticed when their connection closes
+        let _ = connection.send(message);
+    }
+}
+
+///Connects to the server and says hello, returns the connection and the index of the player
+pub fn join(address: &str, name: &str, mode: GameMode) -> Result<(Connection, usize), String> {
+    let mut connection = Connection::connect(address)?;
+    connection.send(&Message::Hello {
+        name: name.to_owned(),
+        mode,
+    })?;
+    match connection.receive()? {
+        Message::Welcome { player } => {
+            println!("Joined as player {}, waiting for others", player + 1);
+            Ok((connection, player))
+        }
+        Message::Error { reason } => Err(format!("Server refused to connect: {}", reason)),
+        message => Err(format!("Expected welcome, got {:?}", message)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellShape, Topology};
+    use std::time::Instant;
+
+    fn board() -> BoardLayout {
+        BoardLayout {
+            width: 3,
+            height: 2,
+            shape: CellShape::Hex,
+            topology: Topology::Wrapped,
+            neighborhood: None,
+            mines_per_cell: 2,
+            mines: vec![[0, 1], [0, 1], [1, 2]],
+        }
+    }
+
+    #[test]
+    fn every_message_survives_encoding() {
+        let messages = vec![
+            Message::Hello {
+                name: "Bob \"the\" player\n".to_owned(),
+                mode: GameMode::Coop,
+            },
+            Message::Progress { percent: 40 },
+            Message::Finished { time_ms: 12_345 },
+            Message::Lost,
+            Message::Act {
+                action: CoopAction::Chord,
+                position: [1, 2],
+                revision: 7,
+            },
+            Message::Cursor { position: [0, 0] },
+            Message::Welcome { player: 1 },
+            Message::Start {
+                board: board(),
+                players: vec!["Alice".to_owned(), "Bob".to_owned()],
+            },
+            Message::PlayerProgress {
+                player: 0,
+                percent: 100,
+            },
+            Message::PlayerFinished {
+                player: 1,
+                time_ms: 999,
+            },
+            Message::PlayerLost { player: 0 },
+            Message::PlayerLeft { player: 1 },
+            Message::Winner {
+                player: 1,
+                time_ms: 999,
+            },
+            Message::CoopStart {
+                board: board(),
+                mine_count: 3,
+                players: vec!["Alice".to_owned()],
+            },
+            Message::Cells {
+                revision: 8,
+                cells: vec![
+                    CellState {
+                        position: [1, 2],
+                        revealed: true,
+                        value: 4,
+                        bombs: 2,
+                        flags: 0,
+                        questioned: false,
+                        owner: None,
+                    },
+                    CellState {
+                        position: [0, 1],
+                        revealed: false,
+                        value: 0,
+                        bombs: 0,
+                        flags: 2,
+                        questioned: false,
+                        owner: Some(1),
+                    },
+                ],
+            },
+            Message::Rejected {
+                position: [0, 1],
+                reason: "Tile is flagged".to_owned(),
+            },
+            Message::PlayerCursor {
+                player: 0,
+                position: [1, 1],
+            },
+            Message::GameOver { won: true },
+            Message::Error {
+                reason: "Bye".to_owned(),
+            },
+        ];
+        for message in messages {
+            let line = encode(&message).unwrap();
+            //reader splits the stream by lines, so a message must never contain a line break
+            assert!(line.ends_with('\n'));
+            assert_eq!(line.trim_end().lines().count(), 1, "{}", line);
+            assert_eq!(decode(&line), Ok(message));
+        }
+    }
+
+    #[test]
+    fn messages_from_the_documentation_can_be_read() {
+        assert_eq!(
+            decode(r#"{"version":2,"type":"hello","name":"Player","mode":"race"}"#),
+            Ok(Message::Hello {
+                name: "Player".to_owned(),
+                mode: GameMode::Race,
+            })
+        );
+        assert_eq!(
+            decode(r#"{"version":2,"type":"progress","percent":40}"#),
+            Ok(Message::Progress { percent: 40 })
+        );
+        //fields that were added later can be left out
+        assert_eq!(
+            decode(
+                r#"{"version":2,"type":"cells","revision":1,"cells":[{"position":[0,0],"revealed":false}]}"#
+            ),
+            Ok(Message::Cells {
+                revision: 1,
+                cells: vec![CellState {
+                    position: [0, 0],
+                    revealed: false,
+                    value: 0,
+                    bombs: 0,
+                    flags: 0,
+                    questioned: false,
+                    owner: None,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn other_versions_and_broken_messages_are_refused() {
+        let error = decode(r#"{"version":1,"type":"hello","name":"Player"}"#).unwrap_err();
+        assert!(error.contains("version 1"), "{}", error);
+        assert!(decode(r#"{"type":"lost"}"#).is_err());
+        assert!(decode(r#"{"version":2,"type":"dance"}"#).is_err());
+        assert!(decode(r#"{"version":2,"type":"progress"}"#).is_err());
+        assert!(decode("lost").is_err());
+    }
+
+    #[test]
+    fn player_that_does_not_say_hello_is_dropped() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            accept_players_within(&listener, 1, GameMode::Race, Duration::from_millis(200))
+        });
+        let silent = Connection::connect(&address).unwrap();
+        let start = Instant::now();
+        let (_, player) = join(&address, "Bob", GameMode::Race).unwrap();
+        assert_eq!(player, 0);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        let (connections, names) = server.join().unwrap().unwrap();
+        assert_eq!(connections.len(), 1);
+        assert_eq!(names, vec!["Bob".to_owned()]);
+        //silent player is told why the connection was closed
+        match silent.receive_timeout(Duration::from_secs(5)) {
+            Ok(Message::Error { reason }) => assert!(reason.contains("seconds"), "{}", reason),
+            message => panic!("Expected error, got {:?}", message),
+        }
+    }
+}
@@ -0,0 +1,439 @@
+/**This file contains command line options, they are parsed before anything else is created
+ * so that window can be made with the right size
+ */
+///Text that is printed when --help is passed
+pub const USAGE: &str = "Usage: minesweeper-ecs [options]
+Options:
+    --width <tiles>          Width of the field
+    --height <tiles>         Height of the field
+    --mines <count>          Number of mines on the field
+    --mines-per-cell <count> Most mines a single tile can have, numbers show the sum of the mines around
+    --seed <number>          Seed used to place mines, same seed gives same fields
+    --tile-size <pixels>     Size of a single tile on screen
+    --difficulty <name>      One of: beginner, intermediate, expert
+    --hex                    Play on a field of hexagons, every tile has 6 neighbors
+    --neighborhood <rules>   Which tiles are neighbors: standard, knight, cross, 5x5 or path to a rules file
+    --wrap                   Edges of the field wrap around, so tiles on the edges have all of their neighbors
+    --hot-seat               Two players take turns on the same field, safe tiles give points and mines take them away
+    --host <port>            Host a race over the network, every player gets the same field and the first to clear it wins
+    --join <address>         Join a race hosted on the address, port is 7878 if it's not given
+    --name <name>            Name that other players see in a race
+    --players <count>        Number of players the host waits for before the game starts, 2 by default
+    --coop                   With --host, --join or --server players share one field instead of racing
+    --server <port>          Only run the server for --players players, without a window or playing
+    --bot                    With --join --coop play without a window using simple rules, for trying the server out
+    --load <save>            Continue the game from the save file
+    --replay <file>          Watch recorded game
+    --help                   Print this message";
+
+pub const MIN_TILE_SIZE: i32 = 10;
+pub const MAX_TILE_SIZE: i32 = 200;
+///Largest field that can be created, mostly to prevent typos from eating all the memory
+pub const MAX_FIELD_SIDE: usize = 1000;
+///Most mines a tile can have on multi-mine fields, every number needs its own texture so there is a limit
+pub const MAX_MINES_PER_CELL: u32 = 3;
+///Largest number a tile can show, when all 8 neighbors have as many mines as they can
+pub const MAX_TILE_VALUE: i32 = 8 * MAX_MINES_PER_CELL as i32;
+
+use crate::grid::{CellShape, Topology};
+use crate::neighborhood::Neighborhood;
+use crate::save::topology_fits;
+use serde::{Deserialize, Serialize};
+
+///Classic difficulties from the original game
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+}
+
+impl Difficulty {
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        match name.to_lowercase().as_str() {
+            "beginner" => Some(Difficulty::Beginner),
+            "intermediate" => Some(Difficulty::Intermediate),
+            "expert" => Some(Difficulty::Expert),
+            _ => None,
+        }
+    }
+
+    ///Returns width, height and mine count of the field
+    pub fn field(&self) -> (usize, usize, u32) {
+        match self {
+            Difficulty::Beginner => (9, 9, 10),
+            Difficulty::Intermediate => (16, 16, 40),
+            Difficulty::Expert => (30, 16, 99),
+        }
+    }
+}
+
+///Everything that can be set from command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub width: usize,
+    pub height: usize,
+    pub mines: u32,
+    pub mines_per_cell: u32,
+    pub seed: Option<u64>,
+    pub tile_size: i32,
+    pub shape: CellShape,
+    pub topology: Topology,
+    pub neighborhood: Option<Neighborhood>,
+    pub hot_seat: bool,
+    ///Port to host a race on
+    pub host: Option<u16>,
+    ///Address of the race to join
+    pub join: Option<String>,
+    pub name: String,
+    pub players: usize,
+    ///Network game is played on a shared field instead of a race
+    pub coop: bool,
+    ///Port to run only the server on
+    pub server: Option<u16>,
+    pub bot: bool,
+    pub load: Option<String>,
+    pub replay: Option<String>,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 10,
+            mines: 10,
+            mines_per_cell: 1,
+            seed: None,
+            tile_size: 50,
+            shape: CellShape::Square,
+            topology: Topology::Bounded,
+            neighborhood: None,
+            hot_seat: false,
+            host: None,
+            join: None,
+            name: "Player".to_owned(),
+            players: 2,
+            coop: false,
+            server: None,
+            bot: false,
+            load: None,
+            replay: None,
+            help: false,
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} requires a value", name))?;
+    value
+        .parse::<T>()
+        .map_err(|_| format!("\"{}\" is not a valid value for {}", value, name))
+}
+
+impl Options {
+    ///Parses options from the arguments, first argument is expected to be the name of the program.
+    /// Values that were not passed are taken from `defaults`
+    pub fn parse<I: Iterator<Item = String>>(
+        args: I,
+        defaults: Options,
+    ) -> Result<Options, String> {
+        let mut options = defaults;
+        let mut width: Option<usize> = None;
+        let mut height: Option<usize> = None;
+        let mut mines: Option<u32> = None;
+        let mut difficulty: Option<Difficulty> = None;
+
+        let mut args = args.skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--width" => width = Some(parse_value(&arg, args.next())?),
+                "--height" => height = Some(parse_value(&arg, args.next())?),
+                "--mines" => mines = Some(parse_value(&arg, args.next())?),
+                "--mines-per-cell" => options.mines_per_cell = parse_value(&arg, args.next())?,
+                "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+                "--tile-size" => options.tile_size = parse_value(&arg, args.next())?,
+                "--difficulty" => {
+                    let name: String = parse_value(&arg, args.next())?;
+                    difficulty = Some(Difficulty::from_name(&name).ok_or(format!(
+                        "Unknown difficulty \"{}\", expected beginner, intermediate or expert",
+                        name
+                    ))?);
+                }
+                "--load" => options.load = Some(parse_value(&arg, args.next())?),
+                "--replay" => options.replay = Some(parse_value(&arg, args.next())?),
+                "--help" | "-h" => options.help = true,
+                "--hex" => options.shape = CellShape::Hex,
+                "--wrap" => options.topology = Topology::Wrapped,
+                "--hot-seat" => options.hot_seat = true,
+                "--host" => options.host = Some(parse_value(&arg, args.next())?),
+                "--join" => options.join = Some(parse_value(&arg, args.next())?),
+                "--name" => options.name = parse_value(&arg, args.next())?,
+                "--players" => options.players = parse_value(&arg, args.next())?,
+                "--coop" => options.coop = true,
+                "--server" => options.server = Some(parse_value(&arg, args.next())?),
+                "--bot" => options.bot = true,
+                "--neighborhood" => {
+                    let value: String = parse_value(&arg, args.next())?;
+                    options.neighborhood = Some(Neighborhood::from_name_or_file(&value)?);
+                }
+                _ => return Err(format!("Unknown option \"{}\"\n{}", arg, USAGE)),
+            }
+        }
+
+        let custom_field = width.is_some() || height.is_some() || mines.is_some();
+        if options.load.is_some() && options.replay.is_some() {
+            return Err("--load and --replay can not be used together".to_owned());
+        }
+        if options.hot_seat && (options.load.is_some() || options.replay.is_some()) {
+            //saves don't have scores and replays remember if players were taking turns
+            return Err("--hot-seat can not be used with --load or --replay".to_owned());
+        }
+        let roles = [
+            options.host.is_some(),
+            options.join.is_some(),
+            options.server.is_some(),
+        ];
+        let network = roles.contains(&true);
+        if roles.iter().filter(|used| **used).count() > 1 {
+            return Err(
+                "Only one of --host, --join and --server can be used, host joins its own game"
+                    .to_owned(),
+            );
+        }
+        if network && (options.hot_seat || options.load.is_some() || options.replay.is_some()) {
+            return Err(
+                "--host, --join and --server can not be used with --hot-seat, --load or --replay"
+                    .to_owned(),
+            );
+        }
+        if options.coop && !network {
+            return Err("--coop needs --host, --join or --server".to_owned());
+        }
+        if options.bot && !(options.coop && options.join.is_some()) {
+            return Err(
+                "--bot can only play co-op games it joins, use it with --join and --coop"
+                    .to_owned(),
+            );
+        }
+        //bot uses the seed for its own moves
+        if options.join.is_some()
+            && (custom_field
+                || difficulty.is_some()
+                || options.seed.is_some() && !options.bot
+                || options.shape != CellShape::Square
+                || options.topology != Topology::Bounded
+                || options.mines_per_cell != 1
+                || options.neighborhood.is_some())
+        {
+            return Err("Field of the network game is chosen by the host, field options can not be used with --join".to_owned());
+        }
+        if options.players == 0 {
+            return Err("Network game needs at least 1 player".to_owned());
+        }
+        if options.name.trim().is_empty() {
+            return Err("--name can not be empty".to_owned());
+        }
+        if options.load.is_some() || options.replay.is_some() {
+            //field is stored in the file, so there is nothing to generate
+            if custom_field
+                || difficulty.is_some()
+                || options.seed.is_some()
+                || options.shape != CellShape::Square
+                || options.topology != Topology::Bounded
+                || options.mines_per_cell != 1
+                || options.neighborhood.is_some()
+            {
+                return Err(
+                    "--width, --height, --mines, --mines-per-cell, --difficulty, --seed, --hex, --wrap and --neighborhood can not be used with --load or --replay, field is taken from the file"
+                        .to_owned(),
+                );
+            }
+        }
+        if let Some(difficulty) = difficulty {
+            if custom_field {
+                return Err(
+                    "--difficulty can not be used together with --width, --height or --mines"
+                        .to_owned(),
+                );
+            }
+            let (width, height, mines) = difficulty.field();
+            options.width = width;
+            options.height = height;
+            options.mines = mines;
+        } else {
+            options.width = width.unwrap_or(options.width);
+            options.height = height.unwrap_or(options.height);
+            options.mines = mines.unwrap_or(options.mines);
+        }
+
+        if options.width == 0 || options.height == 0 {
+            return Err("Field must be at least 1 tile wide and 1 tile high".to_owned());
+        }
+        if options.width > MAX_FIELD_SIDE || options.height > MAX_FIELD_SIDE {
+            return Err(format!(
+                "Field can not be bigger than {0}x{0} tiles",
+                MAX_FIELD_SIDE
+            ));
+        }
+        if !topology_fits(options.shape, options.topology, options.height) {
+            return Err("--wrap with --hex needs even number of rows".to_owned());
+        }
+        if options.mines_per_cell == 0 || options.mines_per_cell > MAX_MINES_PER_CELL {
+            return Err(format!(
+                "Tile can have between 1 and {} mines",
+                MAX_MINES_PER_CELL
+            ));
+        }
+        if let Some(neighborhood) = &options.neighborhood {
+            neighborhood.check_fits(options.shape, options.mines_per_cell)?;
+        }
+        //there has to be at least one tile without a mine, otherwise there is nothing to click
+        let most_mines = (options.width * options.height - 1) * options.mines_per_cell as usize;
+        if options.mines as usize > most_mines {
+            return Err(format!(
+                "{} mines do not fit on {}x{} field, at most {} can be placed",
+                options.mines, options.width, options.height, most_mines
+            ));
+        }
+        if options.tile_size < MIN_TILE_SIZE || options.tile_size > MAX_TILE_SIZE {
+            return Err(format!(
+                "Tile size must be between {} and {} pixels",
+                MIN_TILE_SIZE, MAX_TILE_SIZE
+            ));
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Parses options the way they come from the command line, defaults are the built in ones
+    fn parse(args: &str) -> Result<Options, String> {
+        let args = std::iter::once("minesweeper-ecs")
+            .chain(args.split_whitespace())
+            .map(|arg| arg.to_owned());
+        Options::parse(args, Options::default())
+    }
+
+    #[test]
+    fn options_that_do_not_go_together_are_refused() {
+        let errors = [
+            (
+                "--difficulty expert --width 20",
+                "--difficulty can not be used together with --width, --height or --mines",
+            ),
+            (
+                "--load save.json --replay replay.json",
+                "--load and --replay can not be used together",
+            ),
+            (
+                "--hot-seat --load save.json",
+                "--hot-seat can not be used with --load or --replay",
+            ),
+            ("--coop", "--coop needs --host, --join or --server"),
+            (
+                "--join 127.0.0.1 --bot",
+                "--bot can only play co-op games it joins, use it with --join and --coop",
+            ),
+            (
+                "--host 7878 --coop --bot",
+                "--bot can only play co-op games it joins, use it with --join and --coop",
+            ),
+            (
+                "--join 127.0.0.1 --width 20",
+                "Field of the network game is chosen by the host, field options can not be used with --join",
+            ),
+            (
+                "--join 127.0.0.1 --hex",
+                "Field of the network game is chosen by the host, field options can not be used with --join",
+            ),
+        ];
+        for (args, error) in errors {
+            assert_eq!(parse(args), Err(error.to_owned()), "{}", args);
+        }
+    }
+
+    #[test]
+    fn network_options_that_go_together_are_accepted() {
+        let options = parse("--join 127.0.0.1 --coop --bot --seed 5").unwrap();
+        assert!(options.bot && options.coop);
+        assert_eq!(options.seed, Some(5));
+        assert!(parse("--host 7878 --coop --difficulty expert").is_ok());
+    }
+
+    #[test]
+    fn field_has_to_have_a_tile_without_a_mine() {
+        assert_eq!(parse("--width 3 --height 3 --mines 8").unwrap().mines, 8);
+        assert_eq!(
+            parse("--width 3 --height 3 --mines 9"),
+            Err("9 mines do not fit on 3x3 field, at most 8 can be placed".to_owned())
+        );
+        //every tile but one can hold all of its mines
+        assert_eq!(
+            parse("--width 3 --height 3 --mines 24 --mines-per-cell 3")
+                .unwrap()
+                .mines,
+            24
+        );
+        assert_eq!(
+            parse("--width 3 --height 3 --mines 25 --mines-per-cell 3"),
+            Err("25 mines do not fit on 3x3 field, at most 24 can be placed".to_owned())
+        );
+    }
+
+    #[test]
+    fn field_size_is_limited() {
+        let biggest = format!("--width {0} --height {0} --mines 1", MAX_FIELD_SIDE);
+        assert_eq!(parse(&biggest).unwrap().width, MAX_FIELD_SIDE);
+        let too_wide = format!("--width {} --mines 1", MAX_FIELD_SIDE + 1);
+        assert_eq!(
+            parse(&too_wide),
+            Err(format!(
+                "Field can not be bigger than {0}x{0} tiles",
+                MAX_FIELD_SIDE
+            ))
+        );
+        assert_eq!(
+            parse("--height 0"),
+            Err("Field must be at least 1 tile wide and 1 tile high".to_owned())
+        );
+    }
+
+    #[test]
+    fn tile_size_is_limited() {
+        let error = Err(format!(
+            "Tile size must be between {} and {} pixels",
+            MIN_TILE_SIZE, MAX_TILE_SIZE
+        ));
+        for (size, fits) in [
+            (MIN_TILE_SIZE - 1, false),
+            (MIN_TILE_SIZE, true),
+            (MAX_TILE_SIZE, true),
+            (MAX_TILE_SIZE + 1, false),
+        ] {
+            let options = parse(&format!("--tile-size {}", size));
+            if fits {
+                assert_eq!(options.unwrap().tile_size, size);
+            } else {
+                assert_eq!(options, error);
+            }
+        }
+    }
+
+    #[test]
+    fn wrapped_hex_field_needs_even_number_of_rows() {
+        assert_eq!(
+            parse("--hex --wrap --height 9 --width 10"),
+            Err("--wrap with --hex needs even number of rows".to_owned())
+        );
+        let options = parse("--hex --wrap --height 8 --width 10").unwrap();
+        assert_eq!(options.shape, CellShape::Hex);
+        assert_eq!(options.topology, Topology::Wrapped);
+        //hex field without wrapping can have any number of rows
+        assert!(parse("--hex --height 9").is_ok());
+    }
+}
@@ -0,0 +1,311 @@
+/**This file contains race over the network: every player gets the same field from the host
+ * and the first one to clear it wins. Players only send how far they got, fields are not shared.
+ * Host runs the server on a separate thread and joins it like any other player,
+ * or the server can be run on its own with --server
+ */
+use crate::gameplay::Board;
+use crate::network::{self, accept_players, broadcast, listen, Connection, GameMode, Message};
+use crate::save::BoardLayout;
+use std::net::TcpListener;
+use std::time::Duration;
+
+///How far a player got in the race
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerStatus {
+    ///Percent of the safe tiles that player has revealed
+    Playing(u32),
+    Finished(u64),
+    Lost,
+    Left,
+}
+
+impl PlayerStatus {
+    fn is_done(&self) -> bool {
+        !matches!(self, PlayerStatus::Playing(_))
+    }
+}
+
+///Starts the server for the race on a separate thread. Server waits until `players` players join,
+/// sends the field to everyone and then passes the progress of every player to the others
+pub fn host(port: u16, players: usize, board: BoardLayout) -> Result<(), String> {
+    let listener = listen(port)?;
+    println!("Hosting race for {} players on port {}", players, port);
+    std::thread::spawn(move || {
+        if let Err(e) = serve(listener, players, board) {
+            println!("Race server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+///Runs the server for the race without playing in it, returns once everyone is done
+pub fn run_server(port: u16, players: usize, board: BoardLayout) -> Result<(), String> {
+    let listener = listen(port)?;
+    println!(
+        "Race server for {} players is running on port {}",
+        players, port
+    );
+    serve(listener, players, board)
+}
+
+fn serve(listener: TcpListener, players: usize, board: BoardLayout) -> Result<(), String> {
+    let (mut connections, names) = accept_players(&listener, players, GameMode::Race)?;
+    broadcast(
+        &mut connections,
+        &Message::Start {
+            board,
+            players: names,
+        },
+    );
+    let mut statuses = vec![PlayerStatus::Playing(0); players];
+    let mut winner: Option<usize> = None;
+    while statuses.iter().any(|status| !status.is_done()) {
+        for player in 0..players {
+            let message = match &connections[player] {
+                Some(connection) => connection.try_receive(),
+                None => None,
+            };
+            let answer = match message {
+                None => continue,
+                Some(Ok(Message::Progress { percent })) => {
+                    statuses[player] = PlayerStatus::Playing(percent.min(100));
+                    Message::PlayerProgress {
+                        player,
+                        percent: percent.min(100),
+                    }
+                }
+                Some(Ok(Message::Finished { time_ms })) => {
+                    statuses[player] = PlayerStatus::Finished(time_ms);
+                    Message::PlayerFinished { player, time_ms }
+                }
+                Some(Ok(Message::Lost)) => {
+                    statuses[player] = PlayerStatus::Lost;
+                    Message::PlayerLost { player }
+                }
+                Some(result) => {
+                    //connection was closed or player sent something that can't be understood
+                    let reason = match result {
+                        Ok(message) => format!("Unexpected message {:?}", message),
+                        Err(e) => e,
+                    };
+                    if let Some(connection) = &mut connections[player] {
+                        connection.reject(&reason);
+                    }
+                    connections[player] = None;
+                    statuses[player] = PlayerStatus::Left;
+                    Message::PlayerLeft { player }
+                }
+            };
+            broadcast(&mut connections, &answer);
+            //only the first player to finish wins, the rest still get their time
+            if let (None, PlayerStatus::Finished(time_ms)) = (winner, statuses[player]) {
+                winner = Some(player);
+                broadcast(&mut connections, &Message::Winner { player, time_ms });
+            }
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    Ok(())
+}
+
+///Player's side of the race: sends the progress of the local game and keeps track of everyone else
+pub struct RaceClient {
+    connection: Option<Connection>,
+    ///Index of the local player
+    pub player: usize,
+    pub names: Vec<String>,
+    pub statuses: Vec<PlayerStatus>,
+    pub winner: Option<usize>,
+    ///Field of the race, same for every player
+    pub board: BoardLayout,
+    ///Last progress that was sent, so that it's only sent when it changes
+    sent_percent: Option<u32>,
+}
+
+impl RaceClient {
+    ///Connects to the host and waits until everyone has joined and the race starts
+    pub fn join(address: &str, name: &str) -> Result<RaceClient, String> {
+        let (connection, player) = network::join(address, name, GameMode::Race)?;
+        let (board, names) = match connection.receive()? {
+            Message::Start { board, players } => (board, players),
+            Message::Error { reason } => return Err(format!("Host stopped the race: {}", reason)),
+            message => return Err(format!("Expected start, got {:?}", message)),
+        };
+        board.validate("the race")?;
+        Ok(RaceClient {
+            connection: Some(connection),
+            player,
+            statuses: vec![PlayerStatus::Playing(0); names.len()],
+            names,
+            winner: None,
+            board,
+            sent_percent: None,
+        })
+    }
+
+    fn send(&mut self, message: &Message) {
+        if let Some(connection) = &mut self.connection {
+            if let Err(e) = connection.send(message) {
+                println!("{}", e);
+                self.connection = None;
+            }
+        }
+    }
+
+    ///Sends how many of the safe tiles are revealed on the local board
+    pub fn send_progress(&mut self, board: &Board) {
+        let safe = board
+            .grid
+            .values()
+            .filter(|field| !field.has_bomb())
+            .count();
+        let revealed = board
+            .grid
+            .values()
+            .filter(|field| field.revealed && !field.has_bomb())
+            .count();
+        let percent = (revealed * 100 / safe.max(1)) as u32;
+        if self.sent_percent != Some(percent) {
+            self.sent_percent = Some(percent);
+            self.send(&Message::Progress { percent });
+        }
+    }
+
+    ///Sends the result of the local game
+    pub fn send_result(&mut self, won: bool, time_ms: u64) {
+        self.send(&if won {
+            Message::Finished { time_ms }
+        } else {
+            Message::Lost
+        });
+    }
+
+    ///Reads messages from the host, returns true if anything has changed
+    pub fn update(&mut self) -> bool {
+        let mut changed = false;
+        while let Some(message) = self.connection.as_ref().and_then(|c| c.try_receive()) {
+            changed = true;
+            let (player, status) = match message {
+                Ok(Message::PlayerProgress { player, percent }) => {
+                    (player, PlayerStatus::Playing(percent))
+                }
+                Ok(Message::PlayerFinished { player, time_ms }) => {
+                    (player, PlayerStatus::Finished(time_ms))
+                }
+                Ok(Message::PlayerLost { player }) => (player, PlayerStatus::Lost),
+                Ok(Message::PlayerLeft { player }) => (player, PlayerStatus::Left),
+                Ok(Message::Winner { player, time_ms }) => {
+                    self.winner = Some(player);
+                    println!(
+                        "{} won the race in {:.1} seconds",
+                        self.name(player),
+                        time_ms as f64 / 1000.0
+                    );
+                    continue;
+                }
+                Ok(message) => {
+                    println!("Unexpected message from the host: {:?}", message);
+                    continue;
+                }
+                Err(e) => {
+                    println!("Lost connection to the host: {}", e);
+                    self.connection = None;
+                    continue;
+                }
+            };
+            if let Some(old) = self.statuses.get_mut(player) {
+                *old = status;
+            }
+        }
+        changed
+    }
+
+    fn name(&self, player: usize) -> &str {
+        self.names
+            .get(player)
+            .map(|name| name.as_str())
+            .unwrap_or("?")
+    }
+
+    ///Progress of every player in a single line, it's shown in the title of the window
+    pub fn status_text(&self) -> String {
+        let players: Vec<String> = self
+            .statuses
+            .iter()
+            .enumerate()
+            .map(|(player, status)| {
+                let status = match status {
+                    PlayerStatus::Playing(percent) => format!("{}%", percent),
+                    PlayerStatus::Finished(time_ms) => {
+                        format!("done in {:.1}s", *time_ms as f64 / 1000.0)
+                    }
+                    PlayerStatus::Lost => "lost".to_owned(),
+                    PlayerStatus::Left => "left".to_owned(),
+                };
+                let you = if player == self.player { " (you)" } else { "" };
+                format!("{}{}: {}", self.name(player), you, status)
+            })
+            .collect();
+        let winner = match self.winner {
+            Some(winner) => format!(" - {} wins!", self.name(winner)),
+            None => String::new(),
+        };
+        format!("Race | {}{}", players.join(" | "), winner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellShape, Topology};
+    use std::time::Instant;
+
+    ///Reads messages until the condition is true, fails if it takes too long
+    fn wait_until<F: Fn(&RaceClient) -> bool>(client: &mut RaceClient, done: F) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done(client) {
+            assert!(Instant::now() < deadline, "{}", client.status_text());
+            client.update();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn first_player_to_finish_wins_the_race() {
+        let board = BoardLayout {
+            width: 4,
+            height: 3,
+            shape: CellShape::Square,
+            topology: Topology::Bounded,
+            neighborhood: None,
+            mines_per_cell: 1,
+            mines: vec![[0, 0], [2, 3]],
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server_board = board.clone();
+        let server = std::thread::spawn(move || serve(listener, 2, server_board));
+        //race starts only when both have joined, so one of them has to wait on another thread
+        let other_address = address.clone();
+        let alice = std::thread::spawn(move || RaceClient::join(&other_address, "Alice"));
+        let mut bob = RaceClient::join(&address, "Bob").unwrap();
+        let mut alice = alice.join().unwrap().unwrap();
+        assert_eq!(alice.board, board);
+        assert_eq!(bob.board, board);
+        assert_ne!(alice.player, bob.player);
+        assert_eq!(alice.names, bob.names);
+
+        bob.send_result(false, 1_000);
+        alice.send_result(true, 2_500);
+        wait_until(&mut alice, |client| client.winner.is_some());
+        assert_eq!(alice.winner, Some(alice.player));
+        //loser still gets to know who won
+        wait_until(&mut bob, |client| {
+            client.winner.is_some() && client.statuses.iter().all(PlayerStatus::is_done)
+        });
+        assert_eq!(bob.winner, Some(alice.player));
+        assert_eq!(bob.statuses[alice.player], PlayerStatus::Finished(2_500));
+        assert_eq!(bob.statuses[bob.player], PlayerStatus::Lost);
+        assert_eq!(server.join().unwrap(), Ok(()));
+    }
+}
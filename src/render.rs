@@ -0,0 +1,213 @@
+/**This file contains renderer that draws the whole frame.
+ * Entities are drawn the same way the framework draws them, layer by layer,
+ * except for the board view which draws every visible tile of the board in one pass
+ */
+use crate::board_renderer::{draw_board, BoardView};
+use crate::coop::PlayerCursors;
+use crate::gameplay::Board;
+use game_oxide_framework::components::*;
+use game_oxide_framework::ui;
+use nalgebra::{Vector2, Vector4};
+use sdl2::image::LoadTexture;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator};
+use sdl2::surface::SurfaceRef;
+use sdl2::ttf::Font;
+use specs::{Entity, Join, World, WorldExt};
+use std::collections::HashMap;
+
+///Textures of the game by name, every texture is a part of one of the loaded images
+pub struct TextureAtlas<'a, T> {
+    creator: &'a TextureCreator<T>,
+    images: Vec<Texture<'a>>,
+    ///Index of the image for every file, so that image used by many textures is only loaded once
+    files: HashMap<String, usize>,
+    ///Image and the part of it for every texture
+    textures: HashMap<String, (usize, Rect)>,
+}
+
+impl<'a, T> TextureAtlas<'a, T> {
+    pub fn new(creator: &'a TextureCreator<T>) -> Self {
+        Self {
+            creator,
+            images: Vec::new(),
+            files: HashMap::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    ///Adds image that was drawn by the game, textures can then be loaded from it
+    /// by passing `file` in place of the path
+    pub fn add_image(&mut self, file: &str, surface: &SurfaceRef) -> Result<(), String> {
+        let image = self
+            .creator
+            .create_texture_from_surface(surface)
+            .map_err(|e| e.to_string())?;
+        self.files.insert(file.to_owned(), self.images.len());
+        self.images.push(image);
+        Ok(())
+    }
+
+    ///Is there an image that was loaded from the file or added under this name
+    pub fn has_image(&self, file: &str) -> bool {
+        self.files.contains_key(file)
+    }
+
+    ///Makes part of the image at `path` the texture with the given name,
+    /// texture that already has this name is replaced
+    pub fn load(&mut self, rect: Vector4<i32>, name: String, path: String) -> Result<(), String> {
+        let image = match self.files.get(&path) {
+            Some(image) => *image,
+            None => {
+                let texture = self.creator.load_texture(&path)?;
+                self.images.push(texture);
+                self.files.insert(path, self.images.len() - 1);
+                self.images.len() - 1
+            }
+        };
+        self.textures.insert(
+            name,
+            (
+                image,
+                Rect::new(rect.x, rect.y, rect.z as u32, rect.w as u32),
+            ),
+        );
+        Ok(())
+    }
+
+    ///Image and the part of it that the texture is
+    pub fn get(&self, name: &str) -> Option<(&Texture<'a>, Rect)> {
+        self.textures
+            .get(name)
+            .map(|(image, rect)| (&self.images[*image], *rect))
+    }
+}
+
+///Draws texture with the given name, textures that were not loaded are skipped
+pub fn draw_texture<R: RenderTarget, T>(
+    canvas: &mut Canvas<R>,
+    atlas: &TextureAtlas<T>,
+    name: &str,
+    position: Vector2<i32>,
+    size: Vector2<u32>,
+) -> Result<(), String> {
+    match atlas.get(name) {
+        Some((texture, source)) => canvas.copy(
+            texture,
+            source,
+            Rect::new(position.x, position.y, size.x, size.y),
+        ),
+        None => Ok(()),
+    }
+}
+
+///Draws text so that it fits into the given height, text is never made bigger than the font
+fn draw_text<R: RenderTarget, T>(
+    canvas: &mut Canvas<R>,
+    atlas: &TextureAtlas<T>,
+    font: &Font,
+    text: &Text,
+    position: Vector2<i32>,
+    height: Option<i32>,
+) -> Result<(), String> {
+    let surface = font
+        .render(&text.text)
+        .blended(text.color)
+        .map_err(|e| e.to_string())?;
+    let texture = atlas
+        .creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())?;
+    let scale = match height {
+        Some(height) => (height as f32 / surface.height() as f32).min(1.0),
+        None => 1.0,
+    };
+    let position = position + text.offset;
+    canvas.copy(
+        &texture,
+        None,
+        Rect::new(
+            position.x,
+            position.y,
+            (surface.width() as f32 * scale) as u32,
+            (surface.height() as f32 * scale) as u32,
+        ),
+    )
+}
+
+///Draws every visible renderable in the world, lower layers first.
+/// Text is only drawn if there is a font
+fn draw_world<R: RenderTarget, T>(
+    world: &World,
+    canvas: &mut Canvas<R>,
+    atlas: &TextureAtlas<T>,
+    font: Option<&Font>,
+) -> Result<(), String> {
+    let entities = world.entities();
+    let renderables = world.read_storage::<Renderable>();
+    let positions = world.read_storage::<Position>();
+    let rectangles = world.read_storage::<Rectangle>();
+    let colors = world.read_storage::<Colored>();
+    let sprites = world.read_storage::<Sprite>();
+    let texts = world.read_storage::<Text>();
+    let views = world.read_storage::<BoardView>();
+    let board = world.try_fetch::<Board>();
+    let cursors = world.try_fetch::<PlayerCursors>();
+    let mouse = world
+        .try_fetch::<ui::MouseData>()
+        .map(|mouse| Vector2::new(mouse.x, mouse.y));
+
+    let mut order: Vec<(u32, Entity)> = (&entities, &renderables)
+        .join()
+        .filter(|(_, renderable)| renderable.visible)
+        .map(|(entity, renderable)| (renderable.layer, entity))
+        .collect();
+    //sort is stable, so entities of the same layer are drawn in the order they were made
+    order.sort_by_key(|(layer, _)| *layer);
+    canvas.set_blend_mode(BlendMode::Blend);
+    for (_, entity) in order {
+        if let (Some(view), Some(board)) = (views.get(entity), board.as_deref()) {
+            draw_board(canvas, atlas, view, board, cursors.as_deref(), mouse)?;
+            continue;
+        }
+        let position = positions
+            .get(entity)
+            .map(|position| Vector2::new(position.x, position.y))
+            .unwrap_or_default();
+        let rectangle = rectangles.get(entity);
+        if let (Some(rectangle), Some(colored)) = (rectangle, colors.get(entity)) {
+            canvas.set_draw_color(colored.color);
+            canvas.fill_rect(Rect::new(
+                position.x,
+                position.y,
+                rectangle.width.max(0) as u32,
+                rectangle.height.max(0) as u32,
+            ))?;
+        }
+        if let Some(sprite) = sprites.get(entity).filter(|sprite| sprite.visible) {
+            draw_texture(canvas, atlas, &sprite.name, position, sprite.size)?;
+        }
+        if let (Some(text), Some(font)) = (texts.get(entity), font) {
+            if text.visible && !text.text.is_empty() {
+                let height = rectangle.map(|rectangle| rectangle.height - text.offset.y);
+                draw_text(canvas, atlas, font, text, position, height)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+///Clears the canvas, draws the world on it and shows it
+pub fn render_frame<R: RenderTarget, T>(
+    world: &World,
+    canvas: &mut Canvas<R>,
+    atlas: &TextureAtlas<T>,
+    font: Option<&Font>,
+) -> Result<(), String> {
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    draw_world(world, canvas, atlas, font)?;
+    canvas.present();
+    Ok(())
+}
@@ -0,0 +1,40 @@
+/**This file contains rules of the game that can be changed in the settings,
+ * gameplay code asks the rules instead of reading the settings directly
+ */
+use crate::settings::Settings;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameRules {
+    ///Can player place more flags than there are mines, like in the original game.
+    /// If enabled mine counter goes negative when there are too many flags
+    pub allow_over_flagging: bool,
+    ///Should flagged tile get question mark before going back to normal
+    pub question_marks: bool,
+    ///Should first revealed tile never be a mine
+    pub safe_first_click: bool,
+    ///Is the game won as soon as every safe tile is revealed, without flagging the mines.
+    /// Races are decided by time, so players should not have to flag the last mines
+    pub win_on_clear: bool,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        GameRules::from_settings(&Settings::default())
+    }
+}
+
+impl GameRules {
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self {
+            allow_over_flagging: settings.allow_over_flagging,
+            question_marks: settings.question_marks,
+            safe_first_click: settings.safe_first_click,
+            win_on_clear: false,
+        }
+    }
+
+    ///Checks if player can place one more flag when `flag_count` flags are already placed
+    pub fn can_place_flag(&self, flag_count: i32, total_mine_count: i32) -> bool {
+        self.allow_over_flagging || flag_count < total_mine_count
+    }
+}
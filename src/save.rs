@@ -0,0 +1,166 @@
+/**This file contains save files and replays, both are stored as json.
+ * Positions are stored as [row, column] pairs
+ */
+use crate::grid::{CellShape, Topology};
+use crate::neighborhood::Neighborhood;
+use crate::options::MAX_MINES_PER_CELL;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+///Path to the save that is written when game is closed before it ended
+pub const AUTOSAVE_PATH: &str = "./saves/autosave.json";
+///Path to the replay of the last finished game
+pub const LAST_REPLAY_PATH: &str = "./replays/last.json";
+
+///Hex rows are shifted one way or the other depending on whether they are even,
+/// so wrapped hex field only fits together if the number of rows is even
+pub fn topology_fits(shape: CellShape, topology: Topology, height: usize) -> bool {
+    !(shape == CellShape::Hex && topology == Topology::Wrapped && height % 2 == 1)
+}
+
+fn one_mine_per_cell() -> u32 {
+    1
+}
+
+///Size of the field and positions of all mines
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardLayout {
+    pub width: usize,
+    pub height: usize,
+    ///Files made before hex fields were added don't have it
+    #[serde(default)]
+    pub shape: CellShape,
+    ///Files made before wrapped fields were added don't have it
+    #[serde(default)]
+    pub topology: Topology,
+    ///Neighbors are decided by the shape of the tiles if there is none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub neighborhood: Option<Neighborhood>,
+    ///Files made before multi-mine fields were added don't have it
+    #[serde(default = "one_mine_per_cell")]
+    pub mines_per_cell: u32,
+    ///Tile with several mines is listed once for every mine
+    pub mines: Vec<[usize; 2]>,
+}
+
+impl BoardLayout {
+    pub fn validate(&self, path: &str) -> Result<(), String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("Field in {} is empty", path));
+        }
+        if !topology_fits(self.shape, self.topology, self.height) {
+            return Err(format!(
+                "Wrapped hex field in {} has odd number of rows",
+                path
+            ));
+        }
+        if self.mines_per_cell == 0 || self.mines_per_cell > MAX_MINES_PER_CELL {
+            return Err(format!(
+                "Tiles in {} can have {} mines, but only 1 to {} are supported",
+                path, self.mines_per_cell, MAX_MINES_PER_CELL
+            ));
+        }
+        if let Some(neighborhood) = &self.neighborhood {
+            neighborhood
+                .check_fits(self.shape, self.mines_per_cell)
+                .map_err(|e| format!("{} in {}", e, path))?;
+        }
+        let mut mines_in_tile: HashMap<[usize; 2], u32> = HashMap::new();
+        for mine in &self.mines {
+            if mine[0] >= self.height || mine[1] >= self.width {
+                return Err(format!(
+                    "Mine at [{}, {}] in {} is outside of the field",
+                    mine[0], mine[1], path
+                ));
+            }
+            let count = mines_in_tile.entry(*mine).or_insert(0);
+            *count += 1;
+            if *count > self.mines_per_cell {
+                return Err(format!(
+                    "Tile [{}, {}] in {} has more than {} mines",
+                    mine[0], mine[1], path, self.mines_per_cell
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+///State of the game that can be continued later
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SaveGame {
+    pub board: BoardLayout,
+    pub revealed: Vec<[usize; 2]>,
+    ///Tile with several flags is listed once for every flag
+    pub flagged: Vec<[usize; 2]>,
+    ///Time in milliseconds
+    pub time_ms: u64,
+}
+
+///Things player can do with the field that are recorded in replays
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum ReplayActionKind {
+    Reveal,
+    Flag,
+    Chord,
+    Hint,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ReplayAction {
+    ///Time since the start of the game in milliseconds
+    pub time_ms: u64,
+    pub kind: ReplayActionKind,
+    pub position: [i32; 2],
+}
+
+///Recorded game that can be played back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub board: BoardLayout,
+    pub actions: Vec<ReplayAction>,
+    ///Time it took to finish the game in milliseconds, measured by the game clock
+    #[serde(default)]
+    pub time_ms: u64,
+    ///Two players were taking turns
+    #[serde(default)]
+    pub hot_seat: bool,
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &str) -> Result<T, String> {
+    let data =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path, e))
+}
+
+fn write_json<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    }
+    let data = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+impl SaveGame {
+    pub fn load(path: &str) -> Result<SaveGame, String> {
+        let save: SaveGame = read_json(path)?;
+        save.board.validate(path)?;
+        Ok(save)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        write_json(path, self)
+    }
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let replay: Replay = read_json(path)?;
+        replay.board.validate(path)?;
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        write_json(path, self)
+    }
+}
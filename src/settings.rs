@@ -0,0 +1,325 @@
+/**This file contains user settings that are kept between launches,
+ * they are stored as json in the config directory of the platform
+ */
+use crate::assets::DEFAULT_THEME;
+use crate::controls::ControlsConfig;
+use crate::options::{Difficulty, Options};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+///Tile sizes that can be picked from the settings screen
+pub const TILE_SIZES: [i32; 6] = [30, 40, 50, 60, 70, 80];
+
+///Settings that can be changed from the settings screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SettingKind {
+    Difficulty,
+    TileSize,
+    Theme,
+    QuestionMarks,
+    SafeFirstClick,
+    OverFlagging,
+    Sound,
+    ///Not a setting, but a button that closes the settings screen
+    Close,
+}
+
+impl SettingKind {
+    ///Settings in the order they are shown on the screen
+    pub const ALL: [SettingKind; 8] = [
+        SettingKind::Difficulty,
+        SettingKind::TileSize,
+        SettingKind::Theme,
+        SettingKind::QuestionMarks,
+        SettingKind::SafeFirstClick,
+        SettingKind::OverFlagging,
+        SettingKind::Sound,
+        SettingKind::Close,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    ///Difficulty of the field, None means that custom field size is used
+    pub difficulty: Option<Difficulty>,
+    pub custom_width: usize,
+    pub custom_height: usize,
+    pub custom_mines: u32,
+    pub tile_size: i32,
+    ///Name of the texture theme
+    pub theme: String,
+    ///Should right clicking flagged tile put question mark on it
+    pub question_marks: bool,
+    ///Should first revealed tile never be a mine
+    pub safe_first_click: bool,
+    ///Can there be more flags than mines
+    pub allow_over_flagging: bool,
+    pub sound: bool,
+    pub controls: ControlsConfig,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: None,
+            custom_width: 10,
+            custom_height: 10,
+            custom_mines: 10,
+            tile_size: 50,
+            theme: DEFAULT_THEME.to_owned(),
+            question_marks: false,
+            safe_first_click: true,
+            allow_over_flagging: false,
+            sound: true,
+            controls: ControlsConfig::default(),
+        }
+    }
+}
+
+///Directory where settings are stored, follows the conventions of each platform
+pub fn config_dir() -> Option<PathBuf> {
+    let env_path = |name: &str| std::env::var_os(name).map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        env_path("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME").map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env_path("XDG_CONFIG_HOME").or_else(|| env_path("HOME").map(|home| home.join(".config")))
+    };
+    base.map(|base| base.join("minesweeper-ecs"))
+}
+
+impl Settings {
+    ///Path to the settings file, None if platform has no config directory
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join("settings.json"))
+    }
+
+    ///Loads settings from the settings file, if file does not exist default settings are used
+    pub fn load() -> Settings {
+        match Settings::path() {
+            Some(path) => Settings::load_from(&path),
+            None => Settings::default(),
+        }
+    }
+
+    ///Loads settings from the given file. File that can't be read or used is reported
+    /// and default settings are used instead, so that broken settings don't stop the game from starting
+    pub fn load_from(path: &Path) -> Settings {
+        match Settings::read(path) {
+            Ok(settings) => settings,
+            Err(e) => {
+                println!("Warning: {}, default settings are used", e);
+                Settings::default()
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Settings, String> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+        };
+        let settings: Settings = serde_json::from_str(&data)
+            .map_err(|e| format!("Failed to parse {:?}: {}", path, e))?;
+        settings
+            .controls
+            .validate()
+            .map_err(|e| format!("{} in {:?}", e, path))?;
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Settings::path().ok_or("Could not find config directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        }
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+    }
+
+    ///Returns width, height and mine count of the field
+    pub fn field(&self) -> (usize, usize, u32) {
+        match self.difficulty {
+            Some(difficulty) => difficulty.field(),
+            None => (self.custom_width, self.custom_height, self.custom_mines),
+        }
+    }
+
+    ///Options that are used if they were not passed from the command line
+    pub fn default_options(&self) -> Options {
+        let (width, height, mines) = self.field();
+        Options {
+            width,
+            height,
+            mines,
+            tile_size: self.tile_size,
+            ..Options::default()
+        }
+    }
+
+    ///Changes setting to the next value, or to the previous one if `forward` is false.
+    /// `themes` are names of all themes that can be picked
+    pub fn change(&mut self, kind: SettingKind, forward: bool, themes: &[String]) {
+        match kind {
+            SettingKind::Difficulty => {
+                let order = [
+                    None,
+                    Some(Difficulty::Beginner),
+                    Some(Difficulty::Intermediate),
+                    Some(Difficulty::Expert),
+                ];
+                let current = order
+                    .iter()
+                    .position(|difficulty| *difficulty == self.difficulty)
+                    .unwrap_or(0);
+                self.difficulty = order[cycle(current, order.len(), forward)];
+            }
+            SettingKind::TileSize => {
+                let current = TILE_SIZES
+                    .iter()
+                    .position(|size| *size >= self.tile_size)
+                    .unwrap_or(0);
+                self.tile_size = TILE_SIZES[cycle(current, TILE_SIZES.len(), forward)];
+            }
+            SettingKind::Theme => {
+                if themes.is_empty() {
+                    return;
+                }
+                let current = themes
+                    .iter()
+                    .position(|theme| *theme == self.theme)
+                    .unwrap_or(0);
+                self.theme = themes[cycle(current, themes.len(), forward)].clone();
+            }
+            SettingKind::QuestionMarks => self.question_marks = !self.question_marks,
+            SettingKind::SafeFirstClick => self.safe_first_click = !self.safe_first_click,
+            SettingKind::OverFlagging => self.allow_over_flagging = !self.allow_over_flagging,
+            SettingKind::Sound => self.sound = !self.sound,
+            SettingKind::Close => {}
+        }
+    }
+
+    ///Text that is displayed on the button of the setting
+    pub fn label(&self, kind: SettingKind) -> String {
+        let on_off = |value: bool| if value { "On" } else { "Off" };
+        match kind {
+            SettingKind::Difficulty => {
+                let (width, height, mines) = self.field();
+                let name = match self.difficulty {
+                    Some(Difficulty::Beginner) => "Beginner",
+                    Some(Difficulty::Intermediate) => "Intermediate",
+                    Some(Difficulty::Expert) => "Expert",
+                    None => "Custom",
+                };
+                format!("Difficulty: {} {}x{} {}*", name, width, height, mines)
+            }
+            SettingKind::TileSize => format!("Tile size: {}*", self.tile_size),
+            SettingKind::Theme => format!("Theme: {}", self.theme),
+            SettingKind::QuestionMarks => {
+                format!("Question marks: {}", on_off(self.question_marks))
+            }
+            SettingKind::SafeFirstClick => {
+                format!("Safe first click: {}", on_off(self.safe_first_click))
+            }
+            SettingKind::OverFlagging => format!(
+                "More flags than mines: {}",
+                on_off(self.allow_over_flagging)
+            ),
+            SettingKind::Sound => format!("Sound: {}", on_off(self.sound)),
+            SettingKind::Close => "Close (* applied on next launch)".to_owned(),
+        }
+    }
+}
+
+fn cycle(current: usize, len: usize, forward: bool) -> usize {
+    if forward {
+        (current + 1) % len
+    } else {
+        (current + len - 1) % len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Settings are compared as json, so that every field is compared
+    fn as_json(settings: &Settings) -> serde_json::Value {
+        serde_json::to_value(settings).unwrap()
+    }
+
+    #[test]
+    fn malformed_or_missing_file_loads_default_settings() {
+        let directory = std::env::temp_dir().join("minesweeper-ecs");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(format!("settings-test-{}.json", std::process::id()));
+        std::fs::write(&path, "{\"tile_size\": 70,").unwrap();
+        let loaded = Settings::load_from(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(as_json(&loaded), as_json(&Settings::default()));
+
+        let loaded = Settings::load_from(&path);
+        assert_eq!(as_json(&loaded), as_json(&Settings::default()));
+    }
+
+    #[test]
+    fn change_cycles_both_ways() {
+        let themes = vec!["default".to_owned(), "dark".to_owned()];
+        let mut settings = Settings::default();
+
+        settings.change(SettingKind::Difficulty, true, &themes);
+        assert_eq!(settings.difficulty, Some(Difficulty::Beginner));
+        settings.change(SettingKind::Difficulty, false, &themes);
+        settings.change(SettingKind::Difficulty, false, &themes);
+        assert_eq!(settings.difficulty, Some(Difficulty::Expert));
+        settings.change(SettingKind::Difficulty, true, &themes);
+        assert_eq!(settings.difficulty, None);
+
+        settings.tile_size = TILE_SIZES[TILE_SIZES.len() - 1];
+        settings.change(SettingKind::TileSize, true, &themes);
+        assert_eq!(settings.tile_size, TILE_SIZES[0]);
+        settings.change(SettingKind::TileSize, false, &themes);
+        assert_eq!(settings.tile_size, TILE_SIZES[TILE_SIZES.len() - 1]);
+
+        settings.change(SettingKind::Theme, false, &themes);
+        assert_eq!(settings.theme, "dark");
+        settings.change(SettingKind::Theme, true, &themes);
+        assert_eq!(settings.theme, "default");
+
+        settings.change(SettingKind::Sound, true, &themes);
+        assert!(!settings.sound);
+        settings.change(SettingKind::Sound, false, &themes);
+        assert!(settings.sound);
+    }
+
+    #[test]
+    fn theme_is_kept_when_there_are_no_themes() {
+        let mut settings = Settings {
+            theme: "dark".to_owned(),
+            ..Settings::default()
+        };
+        settings.change(SettingKind::Theme, true, &[]);
+        settings.change(SettingKind::Theme, false, &[]);
+        assert_eq!(settings.theme, "dark");
+    }
+
+    #[test]
+    fn missing_fields_are_taken_from_default_settings() {
+        let settings: Settings =
+            serde_json::from_str("{\"tile_size\": 70, \"sound\": false}").unwrap();
+        let expected = Settings {
+            tile_size: 70,
+            sound: false,
+            ..Settings::default()
+        };
+        assert_eq!(as_json(&settings), as_json(&expected));
+
+        let saved = serde_json::to_string(&settings).unwrap();
+        let loaded: Settings = serde_json::from_str(&saved).unwrap();
+        assert_eq!(as_json(&loaded), as_json(&expected));
+    }
+}